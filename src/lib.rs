@@ -0,0 +1,1039 @@
+//! Scrapes known color names and their RGB values from the Wikipedia.
+
+use clap::ValueEnum;
+use encoding_rs::{Encoding, UTF_8};
+use futures_util::{stream, Stream, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+use reqwest::header::{CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use reqwest::Client;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The Wikipedia page the colors are scraped from.
+pub const URL: &str = "https://en.wikipedia.org/wiki/List_of_colors_(alphabetical)";
+
+#[derive(Debug)]
+enum Component {
+    Name(String),
+    Rgb(u8, u8, u8),
+}
+
+/// How [`fetch_page_with`] reports download progress on stderr.
+#[derive(Debug, Copy, Clone, Default, ValueEnum)]
+pub enum Progress {
+    /// A progress bar for people
+    #[default]
+    Bar,
+    /// One `{"phase":"fetch","bytes":...}` JSON object per line
+    Json,
+}
+
+/// How a computed channel is turned back into a whole number.
+#[derive(Debug, Copy, Clone, Default, ValueEnum)]
+pub enum Rounding {
+    /// To the nearest value, with halves rounded up
+    #[default]
+    Nearest,
+    /// Down to the value below
+    Floor,
+    /// Up to the value above
+    Ceil,
+}
+
+/// How many channels [`to_u8`] has clamped so far.
+static CLAMPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Converts a computed channel to a `u8` with `mode`, clamping to `0..=255`
+/// rather than letting a cast wrap or saturate unnoticed. Every transform
+/// goes through here, so [`clamped_channels`] can report how often it did.
+pub fn to_u8(value: f64, mode: Rounding) -> u8 {
+    // Conversions like HSL leave values a hair off a whole number, which would
+    // otherwise floor or ceil to the wrong side of it.
+    let value = if (value - value.round()).abs() < 1e-9 {
+        value.round()
+    } else {
+        value
+    };
+    let value = match mode {
+        Rounding::Nearest => value.round(),
+        Rounding::Floor => value.floor(),
+        Rounding::Ceil => value.ceil(),
+    };
+
+    if !(0.0..=255.0).contains(&value) {
+        CLAMPED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    value.clamp(0.0, 255.0) as u8
+}
+
+/// The number of out-of-range channels [`to_u8`] has clamped in this process.
+pub fn clamped_channels() -> usize {
+    CLAMPED.load(Ordering::Relaxed)
+}
+
+/// Decodes a gamma-encoded sRGB channel to linear light in `0.0..=1.0` (the
+/// standard sRGB EOTF).
+pub fn linearize(channel: u8) -> f64 {
+    let channel = channel as f64 / 255.0;
+
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The hue families returned by [`Color::hue_family`], in color wheel order.
+pub const HUE_FAMILIES: &[&str] = &[
+    "red", "orange", "yellow", "green", "cyan", "blue", "purple", "pink", "gray",
+];
+
+/// A named color and its RGB channels.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Color {
+    pub name: String,
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    /// Builds a color from a hue in degrees and saturation/lightness in `0.0..=1.0`.
+    pub fn from_hsl(
+        name: String,
+        hue: f64,
+        saturation: f64,
+        lightness: f64,
+        rounding: Rounding,
+    ) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (red, green, blue) = match hue as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = lightness - chroma / 2.0;
+        let channel = move |value: f64| to_u8((value + m) * 255.0, rounding);
+
+        Color {
+            name,
+            red: channel(red),
+            green: channel(green),
+            blue: channel(blue),
+        }
+    }
+
+    /// Multiplies every channel by `factor`, clamping to `0..=255` rather than
+    /// wrapping.
+    pub fn scale(&self, factor: f64, rounding: Rounding) -> Color {
+        let channel = |value: u8| to_u8(value as f64 * factor, rounding);
+
+        Color {
+            name: self.name.clone(),
+            red: channel(self.red),
+            green: channel(self.green),
+            blue: channel(self.blue),
+        }
+    }
+
+    /// Mirrors the HSL lightness while keeping the hue and saturation, so a
+    /// light shade becomes the matching dark one and vice versa.
+    pub fn invert_lightness(&self, rounding: Rounding) -> Color {
+        let (hue, saturation, lightness) = self.to_hsl();
+
+        Color::from_hsl(
+            self.name.clone(),
+            hue,
+            saturation,
+            1.0 - lightness,
+            rounding,
+        )
+    }
+
+    /// Packs the channels into a single `0x00RRGGBB` integer.
+    pub fn to_u32(&self) -> u32 {
+        (self.red as u32) << 16 | (self.green as u32) << 8 | self.blue as u32
+    }
+
+    /// Converts the color to HSL as `(hue, saturation, lightness)` with the hue in
+    /// degrees and the rest in `0.0..=1.0`. Achromatic colors have a hue of `0.0`.
+    pub fn to_hsl(&self) -> (f64, f64, f64) {
+        let red = self.red as f64 / 255.0;
+        let green = self.green as f64 / 255.0;
+        let blue = self.blue as f64 / 255.0;
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let chroma = max - min;
+        let lightness = (max + min) / 2.0;
+
+        if chroma == 0.0 {
+            return (0.0, 0.0, lightness);
+        }
+
+        let hue = if max == red {
+            ((green - blue) / chroma).rem_euclid(6.0)
+        } else if max == green {
+            (blue - red) / chroma + 2.0
+        } else {
+            (red - green) / chroma + 4.0
+        };
+        let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+
+        (hue * 60.0, saturation, lightness)
+    }
+
+    /// Converts the color to HSV as `(hue, saturation, value)` with the hue in
+    /// degrees and the rest in `0.0..=1.0`. Achromatic colors have a hue and
+    /// saturation of `0.0`.
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let (hue, _, _) = self.to_hsl();
+        let max = self.red.max(self.green).max(self.blue) as f64 / 255.0;
+        let min = self.red.min(self.green).min(self.blue) as f64 / 255.0;
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+
+        (hue, saturation, max)
+    }
+
+    /// Classifies the color into one of [`HUE_FAMILIES`] by its dominant hue band.
+    /// Colors with less than 10% saturation, or that are nearly black or white,
+    /// are `gray`.
+    pub fn hue_family(&self) -> &'static str {
+        let (hue, saturation, lightness) = self.to_hsl();
+
+        if saturation < 0.1 || !(0.05..=0.95).contains(&lightness) {
+            return "gray";
+        }
+
+        match hue {
+            h if h < 15.0 => "red",
+            h if h < 45.0 => "orange",
+            h if h < 70.0 => "yellow",
+            h if h < 165.0 => "green",
+            h if h < 195.0 => "cyan",
+            h if h < 255.0 => "blue",
+            h if h < 290.0 => "purple",
+            h if h < 345.0 => "pink",
+            _ => "red",
+        }
+    }
+
+    /// Names the color in words from its [`hue_family`](Self::hue_family),
+    /// e.g. `dark muted blue`. Lightness below 30% is `dark` and above 70% is
+    /// `light`; saturation below 40% is `muted` and above 75% is `vivid`.
+    /// Grays get no saturation word, and the nearly black or white grays that
+    /// [`hue_family`](Self::hue_family) sets apart are just `black` or `white`.
+    pub fn describe(&self) -> String {
+        let (_, saturation, lightness) = self.to_hsl();
+        let family = self.hue_family();
+
+        if lightness < 0.05 {
+            return "black".to_string();
+        }
+
+        if lightness > 0.95 {
+            return "white".to_string();
+        }
+
+        let tone = match lightness {
+            l if l < 0.3 => Some("dark"),
+            l if l > 0.7 => Some("light"),
+            _ => None,
+        };
+        let intensity = match saturation {
+            _ if family == "gray" => None,
+            s if s < 0.4 => Some("muted"),
+            s if s > 0.75 => Some("vivid"),
+            _ => None,
+        };
+
+        [tone, intensity, Some(family)]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Formats the color as a lowercase `#rrggbb` hex code.
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+
+    /// Simple perceived brightness (Rec. 601 luma) of the gamma-encoded channels in
+    /// `0.0..=255.0`.
+    pub fn luma(&self) -> f64 {
+        0.299 * self.red as f64 + 0.587 * self.green as f64 + 0.114 * self.blue as f64
+    }
+
+    /// Converts the color to CIELAB (D65 white point) as `(L*, a*, b*)`.
+    pub fn to_lab(&self) -> (f64, f64, f64) {
+        let (red, green, blue) = (
+            linearize(self.red),
+            linearize(self.green),
+            linearize(self.blue),
+        );
+        let x = (0.4124 * red + 0.3576 * green + 0.1805 * blue) / 0.95047;
+        let y = 0.2126 * red + 0.7152 * green + 0.0722 * blue;
+        let z = (0.0193 * red + 0.1192 * green + 0.9505 * blue) / 1.08883;
+        let f = |t: f64| {
+            if t > 216.0 / 24389.0 {
+                t.cbrt()
+            } else {
+                (24389.0 / 27.0 * t + 16.0) / 116.0
+            }
+        };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+
+        (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+
+    /// WCAG relative luminance in `0.0..=1.0`.
+    pub fn relative_luminance(&self) -> f64 {
+        let linear = |channel: u8| {
+            let channel = channel as f64 / 255.0;
+
+            if channel <= 0.03928 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        0.2126 * linear(self.red) + 0.7152 * linear(self.green) + 0.0722 * linear(self.blue)
+    }
+
+    /// WCAG contrast ratio between two colors, from `1.0` to `21.0`.
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let a = self.relative_luminance();
+        let b = other.relative_luminance();
+
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Picks black or white text, whichever contrasts more with this color as a
+    /// background, along with the achieved contrast ratio.
+    pub fn best_text_color(&self) -> (Color, f64) {
+        let black = Color {
+            name: "black".to_string(),
+            red: 0,
+            green: 0,
+            blue: 0,
+        };
+        let white = Color {
+            name: "white".to_string(),
+            red: 255,
+            green: 255,
+            blue: 255,
+        };
+        let on_black = self.contrast_ratio(&black);
+        let on_white = self.contrast_ratio(&white);
+
+        if on_black >= on_white {
+            (black, on_black)
+        } else {
+            (white, on_white)
+        }
+    }
+
+    /// Alpha-composites this color over an opaque `background` (source-over), where
+    /// `alpha` is this color's opacity in `0.0..=1.0`.
+    pub fn blend_over(&self, background: &Color, alpha: f64, rounding: Rounding) -> Color {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let channel =
+            |fg: u8, bg: u8| to_u8(fg as f64 * alpha + bg as f64 * (1.0 - alpha), rounding);
+
+        Color {
+            name: self.name.clone(),
+            red: channel(self.red, background.red),
+            green: channel(self.green, background.green),
+            blue: channel(self.blue, background.blue),
+        }
+    }
+
+    /// Maps the color to the nearest index of the xterm 256-color palette, using
+    /// either the 6x6x6 color cube (16-231) or the grayscale ramp (232-255).
+    pub fn to_ansi256(&self) -> u8 {
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+        let nearest_level = |channel: u8| {
+            (0..LEVELS.len())
+                .min_by_key(|&index| LEVELS[index].abs_diff(channel))
+                .unwrap()
+        };
+        let (red, green, blue) = (
+            nearest_level(self.red),
+            nearest_level(self.green),
+            nearest_level(self.blue),
+        );
+        let cube = Color {
+            name: String::new(),
+            red: LEVELS[red],
+            green: LEVELS[green],
+            blue: LEVELS[blue],
+        };
+
+        let average = (self.red as u16 + self.green as u16 + self.blue as u16) / 3;
+        let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+        let level = 8 + gray_index * 10;
+        let gray = Color {
+            name: String::new(),
+            red: level,
+            green: level,
+            blue: level,
+        };
+
+        if self.distance(&gray) < self.distance(&cube) {
+            232 + gray_index
+        } else {
+            16 + 36 * red as u8 + 6 * green as u8 + blue as u8
+        }
+    }
+
+    /// Perceptual CIEDE2000 color difference between two colors.
+    pub fn delta_e(&self, other: &Color) -> f64 {
+        let (l1, a1, b1) = self.to_lab();
+        let (l2, a2, b2) = other.to_lab();
+
+        let c1 = a1.hypot(b1);
+        let c2 = a2.hypot(b2);
+        let c_bar = (c1 + c2) / 2.0;
+        let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + 25f64.powi(7))).sqrt());
+        let a1 = a1 * (1.0 + g);
+        let a2 = a2 * (1.0 + g);
+        let c1 = a1.hypot(b1);
+        let c2 = a2.hypot(b2);
+        let hue = |a: f64, b: f64| {
+            if a == 0.0 && b == 0.0 {
+                0.0
+            } else {
+                b.atan2(a).to_degrees().rem_euclid(360.0)
+            }
+        };
+        let h1 = hue(a1, b1);
+        let h2 = hue(a2, b2);
+
+        let delta_l = l2 - l1;
+        let delta_c = c2 - c1;
+        let delta_h = if c1 * c2 == 0.0 {
+            0.0
+        } else if (h2 - h1).abs() <= 180.0 {
+            h2 - h1
+        } else if h2 <= h1 {
+            h2 - h1 + 360.0
+        } else {
+            h2 - h1 - 360.0
+        };
+        let delta_h = 2.0 * (c1 * c2).sqrt() * (delta_h / 2.0).to_radians().sin();
+
+        let l_bar = (l1 + l2) / 2.0;
+        let c_bar = (c1 + c2) / 2.0;
+        let h_bar = if c1 * c2 == 0.0 {
+            h1 + h2
+        } else if (h1 - h2).abs() <= 180.0 {
+            (h1 + h2) / 2.0
+        } else if h1 + h2 < 360.0 {
+            (h1 + h2 + 360.0) / 2.0
+        } else {
+            (h1 + h2 - 360.0) / 2.0
+        };
+        let t = 1.0 - 0.17 * (h_bar - 30.0).to_radians().cos()
+            + 0.24 * (2.0 * h_bar).to_radians().cos()
+            + 0.32 * (3.0 * h_bar + 6.0).to_radians().cos()
+            - 0.20 * (4.0 * h_bar - 63.0).to_radians().cos();
+        let delta_theta = 30.0 * (-((h_bar - 275.0) / 25.0).powi(2)).exp();
+        let r_c = 2.0 * (c_bar.powi(7) / (c_bar.powi(7) + 25f64.powi(7))).sqrt();
+        let s_l = 1.0 + 0.015 * (l_bar - 50.0).powi(2) / (20.0 + (l_bar - 50.0).powi(2)).sqrt();
+        let s_c = 1.0 + 0.045 * c_bar;
+        let s_h = 1.0 + 0.015 * c_bar * t;
+        let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+        ((delta_l / s_l).powi(2)
+            + (delta_c / s_c).powi(2)
+            + (delta_h / s_h).powi(2)
+            + r_t * (delta_c / s_c) * (delta_h / s_h))
+            .sqrt()
+    }
+
+    /// Euclidean distance between two colors in RGB space.
+    pub fn distance(&self, other: &Color) -> f64 {
+        let red = self.red as f64 - other.red as f64;
+        let green = self.green as f64 - other.green as f64;
+        let blue = self.blue as f64 - other.blue as f64;
+
+        (red * red + green * green + blue * blue).sqrt()
+    }
+}
+
+/// Normalizes a display name such as `Light  Blue (Crayola)` into the
+/// `light_blue_crayola` form used for color names. Runs of separators collapse
+/// into a single underscore and leading/trailing ones are dropped.
+pub fn slugify(name: &str) -> String {
+    name.replace(['(', ')'], "")
+        .split(move |c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase()
+}
+
+/// Parses a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` hex code (the `#` is
+/// optional), an `rgb(r, g, b)` function or a bare `r,g,b` triple into red,
+/// green, blue and alpha channels. Colors without an alpha are opaque.
+pub fn parse_color_input(input: &str) -> anyhow::Result<(u8, u8, u8, u8)> {
+    let trimmed = input.trim();
+    let triple = match trimmed.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("rgb(") => Some(
+            trimmed[4..]
+                .strip_suffix(')')
+                .ok_or_else(|| anyhow::anyhow!("`{}` is missing a closing `)`", input))?,
+        ),
+        _ if trimmed.contains(',') => Some(trimmed),
+        _ => None,
+    };
+
+    if let Some(triple) = triple {
+        let channels = triple
+            .split(',')
+            .map(|channel| {
+                channel.trim().parse::<u8>().map_err(|_| {
+                    anyhow::anyhow!(
+                        "`{}` is not a valid channel in `{}`; expected 0 to 255",
+                        channel.trim(),
+                        input
+                    )
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        return match channels[..] {
+            [red, green, blue] => Ok((red, green, blue, 255)),
+            _ => anyhow::bail!(
+                "`{}` has {} channels; expected red, green and blue",
+                input,
+                channels.len()
+            ),
+        };
+    }
+
+    let hex = trimmed.strip_prefix('#').unwrap_or(trimmed);
+    let digits = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|digit| digit as u8))
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| anyhow::anyhow!("`{}` is not a valid hex color", input))?;
+    let channels = match digits.len() {
+        3 | 4 => digits.iter().map(|digit| digit * 17).collect::<Vec<_>>(),
+        6 | 8 => digits
+            .chunks(2)
+            .map(|pair| pair[0] * 16 + pair[1])
+            .collect(),
+        _ => anyhow::bail!("`{}` is not a valid hex color", input),
+    };
+
+    Ok((
+        channels[0],
+        channels[1],
+        channels[2],
+        channels.get(3).copied().unwrap_or(255),
+    ))
+}
+
+async fn download(client: &Client, url: &str, progress: Progress) -> anyhow::Result<String> {
+    let response = client.get(url).send().await?;
+    let length = response.content_length();
+    let charset = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(';')
+                .filter_map(|param| param.trim().split_once('='))
+                .find(|(key, _)| key.eq_ignore_ascii_case("charset"))
+                .map(|(_, charset)| charset.trim_matches('"').to_string())
+        });
+    let bar = match (progress, length) {
+        (Progress::Json, _) => None,
+        (Progress::Bar, Some(length)) => Some(ProgressBar::new(length).with_style(
+            ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({bytes_per_sec})")?,
+        )),
+        (Progress::Bar, None) => Some(ProgressBar::new_spinner().with_style(
+            ProgressStyle::with_template("{spinner} {bytes} ({bytes_per_sec})")?,
+        )),
+    };
+
+    let mut body = Vec::with_capacity(length.unwrap_or_default() as usize);
+    let mut stream = response.bytes_stream();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+
+        body.extend_from_slice(&chunk);
+
+        match &bar {
+            Some(bar) => bar.inc(chunk.len() as u64),
+            None => eprintln!(
+                "{}",
+                serde_json::json!({ "phase": "fetch", "bytes": body.len(), "total": length })
+            ),
+        }
+    }
+
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
+
+    Ok(decode(&body, charset.as_deref()))
+}
+
+/// Decodes a response body using its declared charset, falling back to UTF-8
+/// when the charset is missing or unknown. A byte order mark wins over both.
+fn decode(body: &[u8], charset: Option<&str>) -> String {
+    let encoding = match charset {
+        Some(label) => Encoding::for_label(label.as_bytes()).unwrap_or_else(|| {
+            eprintln!("warning: unknown charset `{}`; decoding as UTF-8", label);
+            UTF_8
+        }),
+        None => UTF_8,
+    };
+    let (text, used, malformed) = encoding.decode(body);
+
+    if used != UTF_8 {
+        eprintln!("warning: the page is encoded as {}, not UTF-8", used.name());
+    }
+
+    if malformed {
+        eprintln!(
+            "warning: the page is not valid {}; some characters were replaced",
+            used.name()
+        );
+    }
+
+    text.into_owned()
+}
+
+/// Downloads and parses the default Wikipedia page.
+pub async fn load_colors() -> anyhow::Result<Vec<Color>> {
+    load_colors_from(URL).await
+}
+
+/// Downloads and parses the colors listed on `url`.
+pub async fn load_colors_from(url: &str) -> anyhow::Result<Vec<Color>> {
+    Ok(parse_colors(&fetch_page(url).await?))
+}
+
+/// Downloads the raw markup of `url`, showing progress on stderr.
+pub async fn fetch_page(url: &str) -> anyhow::Result<String> {
+    fetch_page_with(&Client::new(), url, Progress::Bar).await
+}
+
+/// Like [`fetch_page`], but through `client` and with a choice of how
+/// progress is reported.
+pub async fn fetch_page_with(
+    client: &Client,
+    url: &str,
+    progress: Progress,
+) -> anyhow::Result<String> {
+    download(client, url, progress).await
+}
+
+/// Asks for the headers of `url` with a HEAD request, returning its ETag or,
+/// failing that, its Last-Modified date.
+pub async fn fetch_validator(client: &Client, url: &str) -> anyhow::Result<Option<String>> {
+    let response = client.head(url).send().await?.error_for_status()?;
+    let headers = response.headers();
+
+    Ok(headers
+        .get(ETAG)
+        .or_else(|| headers.get(LAST_MODIFIED))
+        .and_then(|value| value.to_str().ok())
+        .map(ToString::to_string))
+}
+
+/// Streams the colors listed on `url` so callers can process them one at a time.
+pub fn colors(url: &str) -> impl Stream<Item = anyhow::Result<Color>> + '_ {
+    stream::once(async move {
+        let client = Client::new();
+
+        download(&client, url, Progress::Bar).await
+    })
+    .flat_map(|html| match html {
+        Ok(html) => stream::iter(parse_colors(&html).into_iter().map(Ok)).left_stream(),
+        Err(err) => stream::iter([Err(err)]).right_stream(),
+    })
+}
+
+/// Extracts the colors from the markup of a (saved) copy of the page.
+pub fn parse_colors(html: &str) -> Vec<Color> {
+    parse_labeled_colors(html)
+        .into_iter()
+        .map(|(_, color)| color)
+        .collect()
+}
+
+/// Like [`parse_colors`], but pairs each color with the name exactly as it is
+/// displayed on the page, before it was slugified.
+pub fn parse_labeled_colors(html: &str) -> Vec<(String, Color)> {
+    parse_page(html).colors
+}
+
+/// The colors found on a page, along with a description of each element that
+/// was skipped because it didn't look like the list's usual markup.
+#[derive(Debug, Default)]
+pub struct ParsedPage {
+    /// Each color paired with its name as displayed on the page.
+    pub colors: Vec<(String, Color)>,
+    pub warnings: Vec<String>,
+    /// The names (as displayed) that had no readable RGB swatch beside them.
+    pub missing_rgb: Vec<String>,
+}
+
+/// Caps on how much of a page [`parse_page_limited`] reads.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ParseLimits {
+    /// The most paragraph elements (names and swatches) to look at.
+    pub max_nodes: Option<usize>,
+    /// Stop as soon as this many colors have been paired.
+    pub max_colors: Option<usize>,
+}
+
+/// Parses a page tolerantly: malformed swatches and names without a swatch
+/// (or the reverse) are skipped and reported in [`ParsedPage::warnings`].
+/// Every color gets a distinct slug; repeats are suffixed `_2`, `_3` and so on.
+pub fn parse_page(html: &str) -> ParsedPage {
+    parse_page_limited(html, ParseLimits::default())
+}
+
+/// Like [`parse_page`], but stops early once either of `limits` is reached.
+pub fn parse_page_limited(html: &str, limits: ParseLimits) -> ParsedPage {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("div.mw-content-ltr > div > p").unwrap();
+    // The title wraps the channels in mathematical bold "𝗥𝗚𝗕", so only rely on
+    // the parenthesized triple itself. Failing that, use a hex code in the title
+    // or the swatch's own background color.
+    let triple_regex =
+        Regex::new(r"\(\s*(?<red>\d+)[\s,]+(?<green>\d+)[\s,]+(?<blue>\d+)\s*\)").unwrap();
+    let hex_regex = Regex::new(r"#[0-9A-Fa-f]{6}\b").unwrap();
+    let background_regex = Regex::new(r"(?i)background(?:-color)?\s*:\s*(?<value>[^;]+)").unwrap();
+    let color_extractor = move |e: ElementRef| -> Result<Component, String> {
+        let text = e.text().collect::<String>();
+        let text = text.trim();
+
+        if !text.is_empty() {
+            return Ok(Component::Name(text.to_string()));
+        }
+
+        let title = e.attr("title").unwrap_or_default();
+
+        if let Some(caps) = triple_regex.captures(title) {
+            let channel = |key: &str| {
+                caps[key]
+                    .parse::<u8>()
+                    .map_err(|_| format!("the swatch title `{}` has a channel above 255", title))
+            };
+
+            return Ok(Component::Rgb(
+                channel("red")?,
+                channel("green")?,
+                channel("blue")?,
+            ));
+        }
+
+        let fallback = hex_regex.find(title).map(|hex| hex.as_str()).or_else(|| {
+            e.attr("style")
+                .and_then(|style| background_regex.captures(style))
+                .and_then(|caps| caps.name("value"))
+                .map(|value| value.as_str().trim())
+        });
+
+        match fallback.map(parse_color_input) {
+            Some(Ok((red, green, blue, _))) => Ok(Component::Rgb(red, green, blue)),
+            Some(Err(err)) => Err(format!("a swatch has an unreadable color: {}", err)),
+            None if title.is_empty() => {
+                Err("a swatch has no title or background color".to_string())
+            }
+            None => Err(format!("the swatch title `{}` has no RGB value", title)),
+        }
+    };
+    let unpaired = |page: &mut ParsedPage, component: Component| match component {
+        Component::Name(name) => {
+            page.warnings.push(format!("`{}` has no RGB swatch", name));
+            page.missing_rgb.push(name);
+        }
+        Component::Rgb(red, green, blue) => page.warnings.push(format!(
+            "an RGB swatch ({} {} {}) has no name",
+            red, green, blue
+        )),
+    };
+
+    let mut page = ParsedPage::default();
+    let mut pending = None;
+    let mut visited = 0;
+
+    // Names and swatches alternate, in either order, so pair each component
+    // with the one before it unless they are the same kind.
+    for element in document.select(&selector) {
+        if limits.max_colors == Some(page.colors.len()) || limits.max_nodes == Some(visited) {
+            break;
+        }
+
+        visited += 1;
+
+        let component = match color_extractor(element) {
+            Ok(component) => component,
+            Err(warning) => {
+                page.warnings.push(warning);
+                continue;
+            }
+        };
+
+        match (pending.take(), component) {
+            (Some(Component::Name(name)), Component::Rgb(red, green, blue))
+            | (Some(Component::Rgb(red, green, blue)), Component::Name(name)) => {
+                let color = Color {
+                    name: slugify(&name),
+                    red,
+                    green,
+                    blue,
+                };
+
+                page.colors.push((name, color));
+            }
+            (Some(previous), component) => {
+                unpaired(&mut page, previous);
+                pending = Some(component);
+            }
+            (None, component) => pending = Some(component),
+        }
+    }
+
+    // When the node limit cut the page short, the last component's partner
+    // may simply not have been read yet.
+    if let Some(previous) = pending.filter(|_| limits.max_nodes != Some(visited)) {
+        unpaired(&mut page, previous);
+    }
+
+    // Different names can slugify the same way (or the page can repeat one),
+    // so number the repeats to keep the slugs usable as keys.
+    number_repeats(page.colors.iter_mut().map(|(_, color)| color));
+
+    page
+}
+
+/// Suffixes every repeated name with `_2`, `_3` and so on, in order, so that
+/// no two of `colors` share a name.
+pub fn number_repeats<'a>(colors: impl IntoIterator<Item = &'a mut Color>) {
+    let mut used = HashSet::new();
+
+    for color in colors {
+        let slug = color.name.clone();
+        let mut suffix = 1;
+
+        while !used.insert(color.name.clone()) {
+            suffix += 1;
+            color.name = format!("{}_{}", slug, suffix);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color {
+            name: String::new(),
+            red,
+            green,
+            blue,
+        }
+    }
+
+    #[test]
+    fn slugify_collapses_whitespace() {
+        assert_eq!(slugify("Light  Blue (x)"), "light_blue_x");
+        assert_eq!(slugify("  Dark\tGray (X11) "), "dark_gray_x11");
+    }
+
+    #[test]
+    fn color_input_accepts_every_form() {
+        for (input, expected) in [
+            ("#f00", (255, 0, 0, 255)),
+            ("f008", (255, 0, 0, 136)),
+            ("#ff8000", (255, 128, 0, 255)),
+            ("#FF800080", (255, 128, 0, 128)),
+            ("rgb(1, 2, 3)", (1, 2, 3, 255)),
+            ("RGB(1,2,3)", (1, 2, 3, 255)),
+            (" 1, 2, 3 ", (1, 2, 3, 255)),
+        ] {
+            assert_eq!(parse_color_input(input).unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn color_input_explains_malformed_input() {
+        for (input, message) in [
+            ("#ff000", "`#ff000` is not a valid hex color"),
+            ("#ggg", "`#ggg` is not a valid hex color"),
+            ("rgb(1, 2, 3", "`rgb(1, 2, 3` is missing a closing `)`"),
+            (
+                "1, 2",
+                "`1, 2` has 2 channels; expected red, green and blue",
+            ),
+            (
+                "1, 2, 256",
+                "`256` is not a valid channel in `1, 2, 256`; expected 0 to 255",
+            ),
+        ] {
+            assert_eq!(parse_color_input(input).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn text_color_contrasts_with_the_background() {
+        assert_eq!(rgb(20, 20, 60).best_text_color().0.name, "white");
+        assert_eq!(rgb(250, 240, 200).best_text_color().0.name, "black");
+    }
+
+    #[test]
+    fn red_packs_to_ff0000() {
+        assert_eq!(rgb(255, 0, 0).to_u32(), 0xFF0000);
+        assert_eq!(rgb(255, 0, 0).to_u32(), 16711680);
+        assert_eq!(rgb(0x12, 0x34, 0x56).to_u32(), 0x123456);
+    }
+
+    #[test]
+    fn swatches_parse_from_titles_and_inline_styles() {
+        let colors = parse_colors(include_str!("fixtures/self-test.html"))
+            .into_iter()
+            .map(|color| (color.name, color.red, color.green, color.blue))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            colors,
+            [
+                // 𝗥𝗚𝗕 (173 216 230)
+                ("light_blue".to_string(), 173, 216, 230),
+                // Hex #FF0000
+                ("red".to_string(), 255, 0, 0),
+                // background-color: #a9a9a9
+                ("dark_gray_x11".to_string(), 169, 169, 169),
+                // 𝗥𝗚𝗕 (0, 128, 0), before its name
+                ("office_green".to_string(), 0, 128, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_styles_accept_rgb_functions() {
+        let colors = parse_colors(
+            r#"<div class="mw-content-ltr"><div>
+            <p>Teal</p><p style="background: rgb(0, 128, 128)"></p>
+            </div></div>"#,
+        );
+
+        assert_eq!(
+            (colors[0].red, colors[0].green, colors[0].blue),
+            (0, 128, 128)
+        );
+    }
+
+    #[test]
+    fn scaling_clamps_at_the_boundaries() {
+        let scaled = |color: Color, factor| {
+            let color = color.scale(factor, Rounding::Nearest);
+
+            (color.red, color.green, color.blue)
+        };
+
+        assert_eq!(scaled(rgb(255, 200, 1), 1.5), (255, 255, 2));
+        assert_eq!(scaled(rgb(255, 0, 3), 0.5), (128, 0, 2));
+        assert_eq!(scaled(rgb(255, 0, 3), -1.0), (0, 0, 0));
+        assert_eq!(scaled(rgb(255, 0, 3), 0.0), (0, 0, 0));
+    }
+
+    #[test]
+    fn colliding_names_survive_with_distinct_slugs() {
+        let colors = parse_colors(
+            r#"<div class="mw-content-ltr"><div>
+            <p>Light Blue</p><p title="(173 216 230)"></p>
+            <p>Light-Blue</p><p title="(172 216 230)"></p>
+            <p>light blue</p><p title="(171 216 230)"></p>
+            </div></div>"#,
+        );
+        let names = colors
+            .iter()
+            .map(|color| (color.name.as_str(), color.red))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            [
+                ("light_blue", 173),
+                ("light_blue_2", 172),
+                ("light_blue_3", 171)
+            ]
+        );
+    }
+
+    #[test]
+    fn each_rounding_mode_at_a_half() {
+        assert_eq!(to_u8(127.5, Rounding::Nearest), 128);
+        assert_eq!(to_u8(127.5, Rounding::Floor), 127);
+        assert_eq!(to_u8(127.5, Rounding::Ceil), 128);
+        assert_eq!(to_u8(127.4, Rounding::Nearest), 127);
+    }
+
+    #[test]
+    fn rounding_ignores_floating_point_noise() {
+        assert_eq!(to_u8(128.000_000_000_01, Rounding::Ceil), 128);
+        assert_eq!(to_u8(127.999_999_999_99, Rounding::Floor), 128);
+    }
+
+    #[test]
+    fn mid_gray_is_half_linear_light() {
+        assert!((linearize(188) - 0.5).abs() < 0.01);
+        assert_eq!(linearize(0), 0.0);
+        assert_eq!(linearize(255), 1.0);
+    }
+
+    #[test]
+    fn descriptions_combine_tone_intensity_and_family() {
+        assert_eq!(rgb(255, 0, 0).describe(), "vivid red");
+        assert_eq!(rgb(60, 60, 60).describe(), "dark gray");
+        assert_eq!(rgb(50, 55, 80).describe(), "dark muted blue");
+        assert_eq!(rgb(5, 5, 5).describe(), "black");
+    }
+
+    #[test]
+    fn blending_at_the_alpha_extremes() {
+        let blended = |alpha| {
+            let color = rgb(255, 0, 51).blend_over(&rgb(0, 128, 255), alpha, Rounding::Nearest);
+
+            (color.red, color.green, color.blue)
+        };
+
+        assert_eq!(blended(0.0), (0, 128, 255));
+        assert_eq!(blended(1.0), (255, 0, 51));
+        assert_eq!(blended(0.5), (128, 64, 153));
+    }
+
+    #[test]
+    fn out_of_range_channels_clamp_and_are_counted() {
+        let before = clamped_channels();
+
+        assert_eq!(to_u8(300.0, Rounding::Nearest), 255);
+        assert_eq!(to_u8(-8.0, Rounding::Nearest), 0);
+        assert_eq!(to_u8(255.0, Rounding::Nearest), 255);
+        // Other tests clamp too, and run alongside this one.
+        assert!(clamped_channels() >= before + 2);
+    }
+}
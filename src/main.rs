@@ -1,180 +1,5308 @@
-use clap::{Parser, ValueEnum};
+use anyhow::Context;
+use clap::builder::RangedU64ValueParser;
+use clap::parser::ValueSource;
+use clap::{ArgGroup, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use colors::{
+    clamped_channels, fetch_page_with, fetch_validator, linearize, number_repeats,
+    parse_color_input, parse_colors, parse_page, parse_page_limited, slugify, to_u8, Color,
+    ParseLimits, Progress, Rounding, HUE_FAMILIES, URL,
+};
+use comfy_table::{presets, Cell, Color as TableColor, ContentArrangement, Table};
+use font8x8::{UnicodeFonts, BASIC_FONTS};
+use futures_util::future::join_all;
+use handlebars::Handlebars;
+use image::{ImageFormat, Rgb, RgbImage};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 use regex::Regex;
-use reqwest::Client;
-use scraper::{ElementRef, Html, Selector};
+use reqwest::{Client, Proxy, Url};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fmt::Write;
+use std::fs;
+use std::io::{self, IsTerminal, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Instant, SystemTime};
+use tokio::signal;
 
 #[derive(Debug, Copy, Clone, ValueEnum, Default)]
 enum OutputFormat {
+    /// A JSON array of color objects
     Json,
+    /// An XML document of color elements
     Xml,
+    /// Comma-separated values with a header row
     #[default]
     Csv,
+    /// One #rrggbb code per line
+    HexList,
+    /// A JASC (Paint Shop Pro) palette
+    JascPal,
+    /// A GIMP palette
+    Gpl,
+    /// LESS `@name: #rrggbb;` variables
+    Less,
+    /// CSS custom properties in a `:root` block
+    Css,
+    /// A standalone HTML page with a table of swatches
+    Html,
+    /// An Adobe Swatch Exchange file
+    Ase,
+    /// A PNG strip of --swatch-size squares, one per color
+    Image,
+    /// A bordered table for reading in a terminal
+    Table,
+    /// SQL that creates a `colors` table and inserts every color
+    Sql,
+    /// Every other format, one file each in --output-dir
+    All,
+}
+
+impl OutputFormat {
+    fn name(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    fn is_binary(self) -> bool {
+        matches!(self, OutputFormat::Ase | OutputFormat::Image)
+    }
+
+    /// The option the format can't be written without, as `main` enforces:
+    /// binary formats need a file and `all` needs a directory.
+    fn required_output(self) -> Option<&'static str> {
+        match self {
+            OutputFormat::All => Some("--output-dir"),
+            format if format.is_binary() => Some("--output"),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Xml => "xml",
+            OutputFormat::Csv => "csv",
+            OutputFormat::HexList => "txt",
+            OutputFormat::JascPal => "pal",
+            OutputFormat::Gpl => "gpl",
+            OutputFormat::Less => "less",
+            OutputFormat::Css => "css",
+            OutputFormat::Html => "html",
+            OutputFormat::Ase => "ase",
+            OutputFormat::Image => "png",
+            OutputFormat::Table => "table.txt",
+            OutputFormat::Sql => "sql",
+            OutputFormat::All => unreachable!("`all` is expanded before writing"),
+        }
+    }
+
+    /// The format whose extension `path` ends in, preferring the longest match
+    /// so `colors.table.txt` is a table rather than a hex list.
+    fn from_path(path: &Path) -> Option<OutputFormat> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+
+        OutputFormat::All
+            .expand()
+            .into_iter()
+            .filter(|format| name.ends_with(&format!(".{}", format.extension())))
+            .max_by_key(|format| format.extension().len())
+    }
+
+    /// The concrete formats to write, expanding `all` into every other format.
+    fn expand(self) -> Vec<OutputFormat> {
+        match self {
+            OutputFormat::All => OutputFormat::value_variants()
+                .iter()
+                .copied()
+                .filter(|format| !matches!(format, OutputFormat::All))
+                .collect(),
+            format => vec![format],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default)]
+enum ChannelScale {
+    /// Integers from 0 to 255
+    #[default]
+    Byte,
+    /// Normalized floats from 0.0 to 1.0
+    Float,
+    /// Linear-light floats from 0.0 to 1.0; set with --linear
+    #[value(skip)]
+    Linear,
+}
+
+impl ChannelScale {
+    fn format(self, value: u8, precision: usize) -> String {
+        match self {
+            ChannelScale::Byte => value.to_string(),
+            ChannelScale::Float => format!("{:.*}", precision, value as f64 / 255.0),
+            ChannelScale::Linear => format!("{:.*}", precision, linearize(value)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum SortKey {
+    /// Alphabetically by name
+    Name,
+    /// Dark to light by simple (Rec. 601) luma
+    Luminance,
+    /// Dark to light by perceptual CIELAB L* lightness
+    Lightness,
+    /// Around the color wheel, starting at --hue-start, with grays last
+    Hue,
+    /// Muted to vivid by HSV saturation, then dark to light
+    Saturation,
+    /// Dark to bright by HSV value, then dark to light
+    Value,
+    /// Densest regions of RGB space first; depends on which colors are loaded
+    Popularity,
+    /// A smooth ramp where each color is followed by the closest one left,
+    /// from --path-start; a greedy heuristic, not the shortest possible path
+    Path,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default)]
+enum DistanceMetric {
+    /// Straight-line distance in RGB space (0-441)
+    #[default]
+    Euclidean,
+    /// Perceptual CIEDE2000 difference (0-100)
+    Ciede2000,
+}
+
+impl DistanceMetric {
+    fn distance(self, a: &Color, b: &Color) -> f64 {
+        match self {
+            DistanceMetric::Euclidean => a.distance(b),
+            DistanceMetric::Ciede2000 => a.delta_e(b),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum AnsiMode {
+    /// 24-bit RGB escapes
+    Truecolor,
+    /// The xterm 256-color palette
+    #[value(name = "256")]
+    Ansi256,
+    /// No escapes at all
+    Off,
+}
+
+impl AnsiMode {
+    /// Picks truecolor when `COLORTERM` advertises it, else the 256-color palette.
+    fn detect() -> Self {
+        match std::env::var("COLORTERM").as_deref() {
+            Ok("truecolor" | "24bit") => AnsiMode::Truecolor,
+            _ => AnsiMode::Ansi256,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum TableColumn {
+    /// A cell painted in the color, shown only when printing to a terminal
+    Swatch,
+    Name,
+    Hex,
+    Rgb,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default)]
+enum TableStyle {
+    /// Box-drawing characters
+    #[default]
+    Utf8,
+    /// Plain `+`, `-` and `|`
+    Ascii,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default)]
+enum IntFormat {
+    /// Decimal, e.g. 16711680
+    #[default]
+    Dec,
+    /// 0x-prefixed hexadecimal, e.g. 0xff0000
+    Hex,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default)]
+enum SqlDialect {
+    /// Standard SQL, which most databases accept
+    #[default]
+    Ansi,
+    Sqlite,
+    Postgres,
+    Mysql,
+}
+
+impl SqlDialect {
+    fn identifier(self, name: &str) -> String {
+        match self {
+            SqlDialect::Mysql => format!("`{}`", name.replace('`', "``")),
+            _ => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+
+    fn string(self, text: &str) -> String {
+        let text = text.replace('\'', "''");
+
+        match self {
+            // MySQL reads backslashes in strings as escapes by default.
+            SqlDialect::Mysql => format!("'{}'", text.replace('\\', "\\\\")),
+            _ => format!("'{}'", text),
+        }
+    }
+
+    /// The column types of the name and of each channel.
+    fn types(self) -> (&'static str, &'static str) {
+        match self {
+            SqlDialect::Ansi => ("VARCHAR(255)", "SMALLINT"),
+            SqlDialect::Sqlite => ("TEXT", "INTEGER"),
+            SqlDialect::Postgres => ("TEXT", "SMALLINT"),
+            SqlDialect::Mysql => ("VARCHAR(255)", "TINYINT UNSIGNED"),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default)]
+enum CssColorSyntax {
+    /// #rrggbb
+    #[default]
+    Hex,
+    /// rgb(10 20 30)
+    Rgb,
+    /// hsl(120deg 50% 40%)
+    Hsl,
+}
+
+impl CssColorSyntax {
+    fn format(self, color: &Color) -> String {
+        // One decimal place is enough to get the same channels back.
+        let round = |value: f64| {
+            let value = format!("{:.1}", value);
+
+            value
+                .strip_suffix(".0")
+                .map(str::to_string)
+                .unwrap_or(value)
+        };
+
+        match self {
+            CssColorSyntax::Hex => color.hex(),
+            CssColorSyntax::Rgb => format!("rgb({} {} {})", color.red, color.green, color.blue),
+            CssColorSyntax::Hsl => {
+                let (hue, saturation, lightness) = color.to_hsl();
+
+                format!(
+                    "hsl({}deg {}% {}%)",
+                    round(hue),
+                    round(saturation * 100.0),
+                    round(lightness * 100.0)
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default, PartialEq)]
+enum NameCase {
+    /// The slug, e.g. dark_gray_x11
+    #[default]
+    Slug,
+    /// Every word capitalized, e.g. Dark Gray (X11)
+    Title,
+    /// The page name in upper case, e.g. DARK GRAY (X11)
+    Upper,
+    /// The page name in lower case, e.g. dark gray (x11)
+    Lower,
+    /// The name exactly as the page displays it
+    Original,
+}
+
+impl NameCase {
+    /// Renders `slug` in this case from its page name, or from the slug itself
+    /// for colors that didn't come from a page.
+    fn apply(self, slug: &str, label: Option<&str>) -> String {
+        let readable = label
+            .map(str::to_string)
+            .unwrap_or_else(|| slug.replace('_', " "));
+
+        match self {
+            NameCase::Slug => slug.to_string(),
+            NameCase::Title => readable
+                .split(' ')
+                .map(|word| {
+                    let mut chars = word.chars();
+
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+            NameCase::Upper => readable.to_uppercase(),
+            NameCase::Lower => readable.to_lowercase(),
+            NameCase::Original => readable,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default, PartialEq)]
+enum OutputEncoding {
+    /// UTF-8 without a byte order mark
+    #[default]
+    Utf8,
+    /// UTF-16, little-endian, with a byte order mark
+    Utf16le,
+    /// ISO-8859-1; fails on characters it can't represent
+    Latin1,
+}
+
+impl OutputEncoding {
+    fn encode(self, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        if self == OutputEncoding::Utf8 {
+            return Ok(data);
+        }
+
+        let text = String::from_utf8(data)?;
+
+        // encoding_rs only decodes UTF-16, and its "latin1" is really
+        // windows-1252, so both are encoded by hand.
+        match self {
+            OutputEncoding::Utf8 => unreachable!(),
+            OutputEncoding::Utf16le => Ok([0xFF, 0xFE]
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect()),
+            OutputEncoding::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| {
+                        anyhow::anyhow!(
+                            "`{}` (U+{:04X}) can't be written as latin1; use --output-encoding utf8 or utf16le",
+                            c,
+                            c as u32
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// The name of the encoding for an XML declaration or an HTML charset.
+    fn charset(self) -> &'static str {
+        match self {
+            OutputEncoding::Utf8 => "UTF-8",
+            OutputEncoding::Utf16le => "UTF-16",
+            OutputEncoding::Latin1 => "ISO-8859-1",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum, Default)]
+enum ErrorFormat {
+    /// Human-readable text with the chain of causes
+    #[default]
+    Text,
+    /// A single-line JSON object with `error` and `context` fields
+    Json,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum GroupBy {
+    /// Nest colors by hue family (red, orange, ..., gray)
+    Family,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum Preset {
+    /// Material Design 50-900 shade ramps
+    Material,
+    /// Tailwind CSS 50-950 shade ramps
+    Tailwind,
+    /// The sixteen basic HTML/CSS color keywords
+    Web,
+}
+
+#[derive(Debug, Clone)]
+enum Placeholder {
+    Name,
+    Red,
+    Green,
+    Blue,
+    Hex,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+/// A per-color line template such as `{name}: {hex}`, parsed up front so a
+/// typo in a placeholder is reported before anything is downloaded.
+#[derive(Debug, Clone)]
+struct Template(Vec<Segment>);
+
+impl Template {
+    fn parse(template: &str) -> anyhow::Result<Template> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut key = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => key.push(c),
+                            None => anyhow::bail!("unclosed `{{` in template"),
+                        }
+                    }
+
+                    let placeholder = match key.as_str() {
+                        "name" => Placeholder::Name,
+                        "red" => Placeholder::Red,
+                        "green" => Placeholder::Green,
+                        "blue" => Placeholder::Blue,
+                        "hex" => Placeholder::Hex,
+                        _ => anyhow::bail!(
+                            "unknown placeholder `{{{}}}`; expected one of {{name}}, {{red}}, {{green}}, {{blue}} or {{hex}}",
+                            key
+                        ),
+                    };
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                '}' => anyhow::bail!("unmatched `}}` in template; use `}}}}` for a literal brace"),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Template(segments))
+    }
+
+    fn render(&self, color: &Color) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Placeholder(Placeholder::Name) => color.name.clone(),
+                Segment::Placeholder(Placeholder::Red) => color.red.to_string(),
+                Segment::Placeholder(Placeholder::Green) => color.green.to_string(),
+                Segment::Placeholder(Placeholder::Blue) => color.blue.to_string(),
+                Segment::Placeholder(Placeholder::Hex) => color.hex(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
+#[command(group(
+    ArgGroup::new("randomized")
+        .args(["shuffle", "sample_per_family"])
+        .multiple(true)
+))]
 struct CommandLine {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(
         short,
         long,
-        required = true,
+        global = true,
+        env = "COLORS_FORMAT",
         default_value = "csv",
         default_missing_value = "csv"
     )]
     /// Set the output format
     format: OutputFormat,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "COLORS_OUTPUT",
+        conflicts_with = "clipboard"
+    )]
+    /// Write the output to a file instead of printing it, in the format its
+    /// extension names unless --format is given
+    output: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    /// Copy the output to the system clipboard instead of printing it
+    clipboard: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        global = true,
+        conflicts_with = "clipboard"
+    )]
+    /// Set the text encoding of the output
+    output_encoding: OutputEncoding,
+
+    #[arg(
+        long,
+        requires = "output",
+        conflicts_with_all = [
+            "align",
+            "template",
+            "template_file",
+            "output_encoding",
+            "wrap_width",
+            "separator",
+        ]
+    )]
+    /// Add the colors to the end of --output instead of replacing it, skipping
+    /// names already in the file (csv and hex-list only)
+    append: bool,
+
+    #[arg(long, conflicts_with_all = ["output", "clipboard"])]
+    /// Write each output format to a `colors.<ext>` file in this directory
+    output_dir: Option<PathBuf>,
+
+    #[arg(long)]
+    /// Follow the css `:root` variables with a dark variant of each color in a
+    /// `@media (prefers-color-scheme: dark)` block
+    css_themes: bool,
+
+    #[arg(long, value_enum, default_value_t)]
+    /// Set how the css format writes each color
+    css_color_syntax: CssColorSyntax,
+
+    #[arg(long, default_value = "")]
+    /// Put this before every variable name of the css and less formats
+    name_prefix: String,
+
+    #[arg(long, default_value = "")]
+    /// Put this after every variable name of the css and less formats
+    name_suffix: String,
+
+    #[arg(long, value_enum, default_value_t)]
+    /// Set the database the sql format quotes names and picks column types for
+    sql_dialect: SqlDialect,
+
+    #[arg(long, requires = "output_dir")]
+    /// Write reds.csv, greens.csv and blues.csv to --output-dir, each with the
+    /// name and a single channel
+    split_channels: bool,
+
+    #[arg(long, requires = "output_dir")]
+    /// Also write a manifest.json listing each file's format, size and SHA-256
+    /// hash, and where and when the colors were scraped
+    manifest: bool,
+
+    #[arg(
+        long,
+        value_name = "THRESHOLD",
+        conflicts_with_all = ["output_dir", "preview", "template"]
+    )]
+    /// List the pairs of colors within this CIEDE2000 Delta-E of each other
+    /// instead of the colors themselves
+    near_duplicates: Option<f64>,
+
+    #[arg(long, value_parser = Template::parse, conflicts_with_all = ["output_dir", "preview"])]
+    /// Render each color with a template instead of --format, using the
+    /// placeholders {name}, {red}, {green}, {blue} and {hex}
+    template: Option<Template>,
+
+    #[arg(long, conflicts_with_all = ["output_dir", "preview", "template"])]
+    /// Render the whole list through a Handlebars template file instead of
+    /// --format; it receives `count` and `colors`, each with name, red, green,
+    /// blue, hex and hsl
+    template_file: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    /// Rename colors with a JSON object or a two-column `key,name` CSV whose
+    /// keys are slugs or #rrggbb / rgb(r, g, b) colors; unmatched colors keep
+    /// their names
+    names_from: Option<PathBuf>,
+
+    #[arg(long, requires = "template")]
+    /// Text written once before the templated colors
+    header: Option<String>,
+
+    #[arg(long, requires = "template")]
+    /// Text written once after the templated colors
+    footer: Option<String>,
+
+    #[arg(long)]
+    /// Pad CSV columns to a consistent width for reading in a terminal
+    align: bool,
+
+    #[arg(long, value_enum, default_value_t)]
+    /// Set how JSON channel values are written
+    channel_scale: ChannelScale,
+
+    #[arg(long, conflicts_with = "channel_scale")]
+    /// Write json and csv channels as linear-light floats from 0.0 to 1.0
+    /// (sRGB decoded) instead of gamma-encoded integers, e.g. for blending in
+    /// shaders
+    linear: bool,
+
+    #[arg(long, default_value_t = 3)]
+    /// Set the number of decimal places for float channels
+    channel_precision: usize,
+
+    #[arg(long, global = true, default_value_t = 100_000)]
+    /// Fail as soon as more than this many colors are parsed
+    max_colors: usize,
+
+    #[arg(long, global = true, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    /// Stop parsing after the first N colors on the page
+    limit: Option<usize>,
+
+    #[arg(long, global = true, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    /// Only look at the first N name and swatch elements of the page
+    max_nodes: Option<usize>,
+
+    #[arg(long, global = true)]
+    /// Only keep colors whose name matches this regex
+    filter: Option<Regex>,
+
+    #[arg(long, global = true)]
+    /// Drop colors whose name matches this regex
+    exclude: Option<Regex>,
+
+    #[arg(long)]
+    /// Add a column saying whether black or white text reads better on each
+    /// color
+    include_text_color: bool,
+
+    #[arg(long)]
+    /// Add a column with the color packed into a 24-bit 0xRRGGBB integer
+    with_int: bool,
+
+    #[arg(long, default_value_t = 1)]
+    /// Set how many threads compute the extra columns (0 uses every core)
+    jobs: usize,
+
+    #[arg(long, value_enum, default_value_t, requires = "with_int")]
+    /// Set how the --with-int column is written
+    int_format: IntFormat,
+
+    #[arg(long, visible_alias = "html-nav")]
+    /// Start the HTML output with an alphabetical index linking to each color
+    with_index: bool,
+
+    #[arg(long, default_value = "Colors")]
+    /// Set the palette name written in the GIMP palette header
+    palette_name: String,
+
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    /// Set how many codes each line of the hex-list format holds
+    wrap_width: usize,
+
+    #[arg(long, default_value = " ")]
+    /// Set what separates the codes of the hex-list format, e.g. ", " for an
+    /// array literal
+    separator: String,
+
+    #[arg(long, default_value_t = 16)]
+    /// Set how many columns GIMP lays the palette's swatches out in
+    palette_columns: usize,
+
+    #[arg(long, value_delimiter = ',', default_value = "swatch,name,hex,rgb")]
+    /// Set which columns the table format shows, in order
+    table_columns: Vec<TableColumn>,
+
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    /// Wrap the table format to fit this many characters [default: unlimited]
+    table_width: Option<u16>,
+
+    #[arg(long, value_enum, default_value_t)]
+    /// Set the characters the table format draws its borders with
+    table_style: TableStyle,
+
+    #[arg(long)]
+    /// Map the scraped colors onto a well-known palette structure
+    preset: Option<Preset>,
+
+    #[arg(long, global = true, env = "COLORS_URL", default_value = URL)]
+    /// Set the page to scrape the colors from (repeatable; the pages are
+    /// downloaded concurrently and merged in the order given)
+    url: Vec<String>,
+
+    #[arg(long, global = true)]
+    /// Send every request through this proxy [default: from HTTPS_PROXY,
+    /// HTTP_PROXY and NO_PROXY]
+    proxy: Option<String>,
+
+    #[arg(long, global = true, value_parser = parse_selector)]
+    /// Also scrape the page linked by the first element matching this CSS
+    /// selector, and so on from each page, e.g. `a.next`
+    next_selector: Option<Selector>,
+
+    #[arg(
+        long,
+        global = true,
+        default_value_t = 10,
+        requires = "next_selector",
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    /// Set how many pages --next-selector follows, starting page included
+    max_pages: usize,
+
+    #[arg(short, long, global = true)]
+    /// Parse a saved copy of the page instead of downloading it (repeatable)
+    input: Vec<PathBuf>,
+
+    #[arg(short, long, global = true)]
+    /// Print diagnostic information to stderr
+    verbose: bool,
+
+    #[arg(long, hide = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    /// Parse the page N times and print the mean and median parse time to
+    /// stderr instead of writing any output
+    bench_parse: Option<u32>,
+
+    #[arg(long, global = true, env = "COLORS_CACHE_DIR")]
+    /// Set where snapshots of each scrape are kept for `diff` [default: the
+    /// user cache directory]
+    cache_dir: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    /// Don't save a snapshot of this scrape to the cache
+    no_cache: bool,
+
+    #[arg(long, global = true)]
+    /// Scrape the page for get, nearest and matrix even if a snapshot of it is
+    /// cached
+    refresh: bool,
+
+    #[arg(long, global = true)]
+    /// Exit with status 3 and no output if every --url has the same ETag or
+    /// Last-Modified as at the last scrape with this flag
+    skip_if_unchanged: bool,
+
+    #[arg(long, value_enum, default_value_t, global = true)]
+    /// Set how download and parse progress is reported on stderr
+    progress_format: Progress,
+
+    #[arg(long, value_enum, default_value_t, global = true)]
+    /// Set how a failure is reported on stderr
+    error_format: ErrorFormat,
+
+    #[arg(long, global = true)]
+    /// Warn on stderr about different page names that became the same slug
+    report_collisions: bool,
+
+    #[arg(long, global = true)]
+    /// Fail instead of warning when part of the page can't be parsed, an
+    /// imported channel isn't a whole number from 0 to 255, or no colors are
+    /// left to write
+    strict: bool,
+
+    #[arg(long, global = true)]
+    /// Fail, listing every offending name, if any name on the page has no
+    /// readable RGB swatch
+    require_rgb: bool,
+
+    #[arg(short, long, global = true)]
+    /// Don't warn when no colors are parsed or left to write
+    quiet: bool,
+
+    #[arg(long, global = true)]
+    /// Accept writing an empty palette, even with --strict
+    allow_empty: bool,
+
+    #[arg(long, value_enum, default_value_t, global = true)]
+    /// Set how the name of each color is written
+    name_case: NameCase,
+
+    #[arg(long, value_enum, default_value_t, global = true)]
+    /// Set how computed channels are rounded back to whole numbers
+    rounding: Rounding,
+
+    #[arg(long)]
+    /// Rescale each channel so its darkest value across the palette becomes 0
+    /// and its brightest 255; every color shifts relative to the whole set
+    stretch: bool,
+
+    #[arg(long)]
+    /// Respace the HSL lightness of the colors evenly from 0% to 100% by rank,
+    /// keeping each color's hue and saturation
+    equalize_lightness: bool,
+
+    #[arg(long, value_name = "FACTOR")]
+    /// Multiply every channel by this factor to lighten or darken the palette
+    scale: Option<f64>,
+
+    #[arg(long, value_name = "COLOR", value_parser = parse_color_input, requires = "alpha")]
+    /// Composite every color at --alpha opacity over this opaque background
+    blend_over: Option<(u8, u8, u8, u8)>,
+
+    #[arg(long, value_parser = parse_opacity, requires = "blend_over")]
+    /// Set the opacity of the colors for --blend-over, from 0 to 1
+    alpha: Option<f64>,
+
+    #[arg(long, conflicts_with = "shuffle")]
+    /// Sort the output by the given key
+    sort: Option<SortKey>,
+
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    /// Set the hue in degrees that `--sort hue` starts from
+    hue_start: f64,
+
+    #[arg(long, value_parser = parse_color_input)]
+    /// Start `--sort path` from the color closest to this one [default: the
+    /// darkest]
+    path_start: Option<(u8, u8, u8, u8)>,
+
+    #[arg(long)]
+    /// Nest the JSON or XML output into groups, or split the HTML table into
+    /// one headed table per group
+    group_by: Option<GroupBy>,
+
+    #[arg(long)]
+    /// Randomize the order of the output
+    shuffle: bool,
+
+    #[arg(long, requires = "randomized")]
+    /// Seed --shuffle and --sample-per-family so the output is reproducible
+    seed: Option<u64>,
+
+    #[arg(long, value_name = "K")]
+    /// Keep at most K randomly chosen colors from each hue family
+    sample_per_family: Option<usize>,
+
+    #[arg(long, requires = "min_distance")]
+    /// Drop colors that are too close to a color kept earlier in the output
+    thin: bool,
+
+    #[arg(long, requires = "thin")]
+    /// Set the minimum distance between colors kept by --thin
+    min_distance: Option<f64>,
+
+    #[arg(long)]
+    /// Merge each run of adjacent colors with the same RGB into its first
+    /// color, adding a count column of how many there were
+    collapse: bool,
+
+    #[arg(long, value_enum, default_value_t, global = true)]
+    /// Set how the distance between two colors is measured
+    distance_metric: DistanceMetric,
+
+    #[arg(long, global = true)]
+    /// Report what would be produced without downloading or writing anything
+    dry_run: bool,
+
+    #[arg(long, conflicts_with = "clipboard")]
+    /// Print truecolor swatches to the terminal instead of the formatted output
+    preview: bool,
+
+    #[arg(long, default_value_t = 32, value_parser = clap::value_parser!(u32).range(1..))]
+    /// Set the width and height of each image swatch in pixels
+    swatch_size: u32,
+
+    #[arg(long)]
+    /// Order the image swatches so each is followed by its most similar color
+    visual_order: bool,
+
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    /// Wrap the image swatches into a grid this many rows tall [default: 1]
+    rows: Option<u32>,
+
+    #[arg(long)]
+    /// Write each color's name under its image swatch, cut to fit
+    label: bool,
+
+    #[arg(long, default_value_t = 4, requires = "preview")]
+    /// Set the width of each preview swatch in columns
+    swatch_width: usize,
+
+    #[arg(long, requires = "preview")]
+    /// Set the escape codes used for preview swatches [default: detected from COLORTERM]
+    ansi: Option<AnsiMode>,
 }
 
-#[derive(Debug)]
-enum Component {
-    Name(String),
-    Rgb(u8, u8, u8),
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Convert a previously exported CSV or JSON file instead of scraping
+    Import {
+        #[arg(long)]
+        /// Path to the exported file
+        file: PathBuf,
+    },
+    /// Look up colors by name
+    Get {
+        #[arg(required_unless_present = "stdin")]
+        /// The color name to look up
+        name: Option<String>,
+
+        #[arg(long)]
+        /// Read one name per line from stdin
+        stdin: bool,
+    },
+    /// Find the closest named colors to arbitrary colors
+    Nearest {
+        #[arg(required_unless_present = "stdin")]
+        /// The color to match as a hex code, rgb(r, g, b) or r,g,b
+        color: Option<String>,
+
+        #[arg(long)]
+        /// Read one color per line from stdin
+        stdin: bool,
+    },
+    /// Compute the pairwise WCAG contrast ratios of a few colors
+    Matrix {
+        /// The color names to compare (defaults to every color left after
+        /// --filter/--exclude)
+        names: Vec<String>,
+    },
+    /// Report the colors added, removed or recolored between two palettes
+    Diff {
+        /// The older palette as exported JSON or CSV [default: the previous
+        /// scrape of --url in the cache]
+        old: Option<PathBuf>,
+
+        /// The newer palette as exported JSON or CSV [default: the current
+        /// colors]
+        new: Option<PathBuf>,
+    },
+    /// Check a palette's colors against the scraped colors of the same names,
+    /// failing if any differ or aren't on the page
+    Validate {
+        /// The palette as exported JSON or CSV
+        file: PathBuf,
+    },
+    /// List the output formats and what each one needs
+    Formats,
+    /// Check the parser and every output format against a bundled page
+    #[command(hide = true, alias = "selftest")]
+    SelfTest,
+    /// Composite a translucent color onto an opaque background
+    Flatten {
+        #[arg(value_parser = parse_color_input)]
+        /// Foreground color as a hex code, with alpha (e.g. #ff000080)
+        color: (u8, u8, u8, u8),
+
+        #[arg(long, value_parser = parse_color_input)]
+        /// Opaque background color as a hex code, rgb(r, g, b) or r,g,b
+        background: (u8, u8, u8, u8),
+    },
+    /// Generate tints (toward white) and shades (toward black) of a color
+    Variants {
+        #[arg(value_parser = parse_color_input)]
+        /// The base color as a hex code, rgb(r, g, b) or r,g,b
+        color: (u8, u8, u8, u8),
+
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+        /// Set how many tints and how many shades to generate
+        steps: u32,
+
+        #[arg(long, default_value = "base")]
+        /// Set the name the variants are numbered after, e.g. base_tint_1
+        name: String,
+    },
+    /// Pick black or white text for the best contrast on a background color
+    TextFor {
+        #[arg(value_parser = parse_color_input)]
+        /// Background color as a hex code, rgb(r, g, b) or r,g,b
+        color: (u8, u8, u8, u8),
+    },
+    /// Describe a color in words, e.g. `dark muted blue`
+    Name {
+        #[arg(value_parser = parse_color_input)]
+        /// The color as a hex code, rgb(r, g, b) or r,g,b
+        color: (u8, u8, u8, u8),
+    },
 }
 
+/// Set once Ctrl-C is pressed, so loops that write several files can stop
+/// between them instead of being killed halfway through one.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// The conventional exit status of a process stopped by SIGINT.
+const INTERRUPTED_EXIT_CODE: u8 = 130;
+
+/// The exit status of a --skip-if-unchanged run that found nothing to do.
+const UNCHANGED_EXIT_CODE: u8 = 3;
+
+/// Stops a --skip-if-unchanged run early, before any output is written.
 #[derive(Debug)]
-struct Color {
-    name: String,
-    red: u8,
-    green: u8,
-    blue: u8,
+struct Unchanged;
+
+impl std::fmt::Display for Unchanged {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("the page is unchanged since the last scrape")
+    }
 }
 
+impl std::error::Error for Unchanged {}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let nodes = load_colors().await?;
-    let args = CommandLine::parse();
+async fn main() -> ExitCode {
+    let matches = CommandLine::command().get_matches();
+    let mut args = CommandLine::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    // Without --format or COLORS_FORMAT, go by the extension of --output.
+    if matches.value_source("format") == Some(ValueSource::DefaultValue) {
+        if let Some(format) = args.output.as_deref().and_then(OutputFormat::from_path) {
+            args.format = format;
+        }
+    }
+
+    let error_format = args.error_format;
+
+    // Handling the signal ourselves keeps the process alive until `run` next
+    // yields, so a file being written synchronously is always finished first.
+    let interrupt = tokio::spawn(async {
+        if signal::ctrl_c().await.is_ok() {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+    });
+
+    let result = tokio::select! {
+        result = run(args) => result,
+        Ok(()) = interrupt => {
+            eprintln!("interrupted");
+
+            return ExitCode::from(INTERRUPTED_EXIT_CODE);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) if err.is::<Unchanged>() => ExitCode::from(UNCHANGED_EXIT_CODE),
+        Err(err) => {
+            report_error(&err, error_format);
+
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                ExitCode::from(INTERRUPTED_EXIT_CODE)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+fn report_error(err: &anyhow::Error, format: ErrorFormat) {
+    match format {
+        ErrorFormat::Text => eprintln!("Error: {:?}", err),
+        ErrorFormat::Json => {
+            let report = serde_json::json!({
+                "error": err.to_string(),
+                "context": err.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+            });
+
+            eprintln!("{}", report);
+        }
+    }
+}
+
+async fn run(args: CommandLine) -> anyhow::Result<()> {
+    if let Some(Command::SelfTest) = args.command {
+        return self_test();
+    }
+
+    if let Some(Command::Formats) = args.command {
+        let data = generate_report(&list_formats(), args.format, args.output_encoding)?;
+
+        return write_output(&args, data.into_bytes());
+    }
+
+    if let Some(Command::TextFor {
+        color: (red, green, blue, _),
+    }) = args.command
+    {
+        let background = Color {
+            name: String::new(),
+            red,
+            green,
+            blue,
+        };
+        let (text, ratio) = background.best_text_color();
+
+        println!("{} {:.2}", text.hex(), ratio);
+
+        return Ok(());
+    }
+
+    if let Some(Command::Name {
+        color: (red, green, blue, _),
+    }) = args.command
+    {
+        let color = Color {
+            name: String::new(),
+            red,
+            green,
+            blue,
+        };
+
+        println!("{}", color.describe());
+
+        return Ok(());
+    }
+
+    if let Some(Command::Flatten {
+        color: (red, green, blue, alpha),
+        background: (bg_red, bg_green, bg_blue, _),
+    }) = args.command
+    {
+        let foreground = Color {
+            name: String::new(),
+            red,
+            green,
+            blue,
+        };
+        let background = Color {
+            name: String::new(),
+            red: bg_red,
+            green: bg_green,
+            blue: bg_blue,
+        };
+
+        println!(
+            "{}",
+            foreground
+                .blend_over(&background, alpha as f64 / 255.0, args.rounding)
+                .hex()
+        );
+
+        return Ok(());
+    }
+
+    // A template replaces the format, so the format's own restrictions don't apply.
+    let formatted = !args.preview && args.template.is_none() && args.template_file.is_none();
+
+    if matches!(args.format, OutputFormat::All) && args.output_dir.is_none() && formatted {
+        anyhow::bail!("the all format writes one file per format and requires --output-dir");
+    }
+
+    if args.format.is_binary() && args.output_dir.is_none() && formatted {
+        if args.output_encoding != OutputEncoding::Utf8 {
+            anyhow::bail!(
+                "the {} format is binary and has no text encoding",
+                args.format.name()
+            );
+        }
+
+        if args.clipboard {
+            anyhow::bail!(
+                "the {} format is binary and cannot be copied to the clipboard",
+                args.format.name()
+            );
+        }
+
+        if args.output.is_none() {
+            anyhow::bail!(
+                "the {} format is binary and requires --output",
+                args.format.name()
+            );
+        }
+    }
+
+    if args.group_by.is_some()
+        && !matches!(
+            args.format,
+            OutputFormat::Json | OutputFormat::Xml | OutputFormat::Html | OutputFormat::All
+        )
+    {
+        anyhow::bail!(
+            "--group-by is only supported by the json, xml and html formats, not {}",
+            args.format.name()
+        );
+    }
+
+    if args.split_channels && !matches!(args.format, OutputFormat::Csv) {
+        anyhow::bail!(
+            "--split-channels only writes csv, not {}",
+            args.format.name()
+        );
+    }
+
+    if args.path_start.is_some() && !matches!(args.sort, Some(SortKey::Path)) {
+        anyhow::bail!("--path-start only applies to --sort path");
+    }
+
+    if args.linear
+        && !matches!(
+            args.format,
+            OutputFormat::Json | OutputFormat::Csv | OutputFormat::All
+        )
+    {
+        anyhow::bail!(
+            "--linear only applies to json and csv, not {}",
+            args.format.name()
+        );
+    }
+
+    if args.css_themes && !matches!(args.format, OutputFormat::Css | OutputFormat::All) {
+        anyhow::bail!(
+            "--css-themes only applies to css, not {}",
+            args.format.name()
+        );
+    }
+
+    if args.append && !matches!(args.format, OutputFormat::Csv | OutputFormat::HexList) {
+        anyhow::bail!(
+            "the {} format cannot be appended to; use csv or hex-list",
+            args.format.name()
+        );
+    }
+
+    let handlebars = args
+        .template_file
+        .as_deref()
+        .map(load_handlebars)
+        .transpose()?;
+
+    if args.dry_run && scrapes(&args) {
+        println!("would download {}", args.url.join(", "));
+        println!("would write {}", describe_destination(&args));
+
+        return Ok(());
+    }
+
+    if let Some(passes) = args.bench_parse {
+        return bench_parse(&args, passes).await;
+    }
+
+    let validators = if args.skip_if_unchanged && scrapes(&args) {
+        Some(check_unchanged(&args).await?)
+    } else {
+        None
+    };
+
+    write_colors(&args, handlebars.as_ref()).await?;
+
+    // Only a scrape that got all the way through may skip the next one.
+    if let Some(validators) = validators {
+        save_validators(&args, &validators)?;
+    }
+
+    Ok(())
+}
+
+/// Loads the colors, transforms them and writes them, or whatever report the
+/// subcommand asks for instead.
+async fn write_colors(
+    args: &CommandLine,
+    handlebars: Option<&Handlebars<'static>>,
+) -> anyhow::Result<()> {
+    // Read the previous snapshot before this scrape replaces it.
+    let previous = match &args.command {
+        Some(Command::Diff { old: Some(old), .. }) => {
+            Some(import_colors(old, args.verbose, args.strict)?)
+        }
+        Some(Command::Diff { old: None, .. }) => {
+            Some(load_snapshot(&cache_dir(args)?, &source_key(args))?)
+        }
+        _ => None,
+    };
+
+    // The page name of each slug, for --name-case.
+    let mut labels = HashMap::new();
+
+    let mut nodes = match &args.command {
+        Some(Command::Import { file }) => import_colors(file, args.verbose, args.strict)?,
+        Some(Command::Variants {
+            color: (red, green, blue, _),
+            steps,
+            name,
+        }) => {
+            let base = Color {
+                name: name.clone(),
+                red: *red,
+                green: *green,
+                blue: *blue,
+            };
+
+            variants(&base, *steps, args.rounding)
+        }
+        Some(Command::Diff { new: Some(new), .. }) => {
+            import_colors(new, args.verbose, args.strict)?
+        }
+        _ if !args.input.is_empty() => unlabel(
+            load_local_colors(&args.input, args.verbose, ParseOptions::from_args(args))?,
+            &mut labels,
+        ),
+        _ if uses_snapshot(args) => {
+            match cache_dir(args).and_then(|dir| load_snapshot(&dir, &source_key(args))) {
+                Ok(nodes) => nodes,
+                Err(err) => {
+                    if args.verbose {
+                        eprintln!("{:#}; scraping instead", err);
+                    }
+
+                    fetch_and_save(args, &mut labels).await?
+                }
+            }
+        }
+        _ => fetch_and_save(args, &mut labels).await?,
+    };
+
+    // Several inputs can each stop at --limit, so apply it to the merged list too.
+    if let Some(limit) = args.limit {
+        nodes.truncate(limit);
+    }
+
+    if nodes.len() > args.max_colors {
+        anyhow::bail!(
+            "parsed more than --max-colors {} colors; is the source the right page?",
+            args.max_colors
+        );
+    }
+
+    let nothing_parsed = nodes.is_empty();
+
+    if nothing_parsed && !args.quiet {
+        eprintln!("warning: no colors were parsed; the page layout may have changed");
+    }
+
+    if let Some(Command::Validate { file }) = &args.command {
+        let palette = import_colors(file, args.verbose, args.strict)?;
+        let report = validate_palette(&palette, &nodes);
+        let failed = report.rows.len();
+        // Unknown colors have no expected value.
+        let unknown = report
+            .rows
+            .iter()
+            .filter(|row| matches!(row[2], Field::Empty))
+            .count();
+
+        write_output(
+            args,
+            generate_report(&report, args.format, args.output_encoding)?.into_bytes(),
+        )?;
+
+        eprintln!(
+            "{} matched, {} mismatched, {} unknown",
+            palette.len() - failed,
+            failed - unknown,
+            unknown
+        );
+
+        if failed > 0 {
+            anyhow::bail!(
+                "{} of the {} colors in {} don't match the page",
+                failed,
+                palette.len(),
+                file.display()
+            );
+        }
+
+        return Ok(());
+    }
+
+    nodes.retain(|color| is_selected(args, color));
+
+    if let Some(mut previous) = previous {
+        previous.retain(|color| is_selected(args, color));
+
+        let data = generate_report(
+            &diff_colors(&previous, &nodes),
+            args.format,
+            args.output_encoding,
+        )?;
+
+        return write_output(args, data.into_bytes());
+    }
+
+    // On the colors in page order, before anything reorders or recolors them.
+    // The counts follow the slugs from here, like the labels do.
+    let runs = args.collapse.then(|| {
+        let counts = collapse_runs(&mut nodes);
+
+        nodes
+            .iter()
+            .map(|color| color.name.clone())
+            .zip(counts)
+            .collect::<HashMap<_, _>>()
+    });
+
+    if let Some(preset) = args.preset {
+        nodes = apply_preset(&nodes, preset, args.rounding);
+    }
+
+    if args.stretch {
+        stretch_channels(&mut nodes, args.rounding);
+    }
+
+    if args.equalize_lightness {
+        nodes = equalize_lightness(&nodes, args.rounding);
+    }
+
+    if let Some(factor) = args.scale {
+        nodes = nodes
+            .iter()
+            .map(|color| color.scale(factor, args.rounding))
+            .collect();
+    }
+
+    if let (Some((red, green, blue, _)), Some(alpha)) = (args.blend_over, args.alpha) {
+        let background = Color {
+            name: String::new(),
+            red,
+            green,
+            blue,
+        };
+
+        nodes = nodes
+            .iter()
+            .map(|color| color.blend_over(&background, alpha, args.rounding))
+            .collect();
+    }
+
+    if let Some(per_family) = args.sample_per_family {
+        nodes = sample_per_family(nodes, per_family, &mut seeded_rng(args.seed));
+    }
+
+    if let Some(key) = args.sort {
+        sort_colors(&mut nodes, key, args.hue_start, args.path_start);
+    }
+
+    if args.shuffle {
+        nodes.shuffle(&mut seeded_rng(args.seed));
+    }
+
+    if let Some(min_distance) = args.min_distance {
+        let before = nodes.len();
+
+        nodes = thin_colors(nodes, min_distance, args.distance_metric);
+
+        if args.verbose {
+            eprintln!("--thin removed {} colors", before - nodes.len());
+        }
+    }
+
+    if args.verbose && clamped_channels() > 0 {
+        eprintln!(
+            "clamped {} out-of-range channels to 0-255",
+            clamped_channels()
+        );
+    }
+
+    let lookup = match &args.command {
+        Some(Command::Get { name, stdin }) => Some(lookup_names(&nodes, name, *stdin)?),
+        Some(Command::Nearest { color, stdin }) => {
+            Some(lookup_nearest(&nodes, color, *stdin, args.distance_metric)?)
+        }
+        _ => None,
+    };
+
+    if let Some(report) = lookup {
+        let data = generate_report(&report, args.format, args.output_encoding)?;
+
+        return write_output(args, data.into_bytes());
+    }
+
+    if let Some(Command::Matrix { names }) = &args.command {
+        let data = generate_matrix(
+            &select_colors(&nodes, names)?,
+            args.format,
+            args.output_encoding,
+        )?;
+
+        return write_output(args, data.into_bytes());
+    }
+
+    if let Some(threshold) = args.near_duplicates {
+        let data = generate_report(
+            &find_near_duplicates(&nodes, threshold),
+            args.format,
+            args.output_encoding,
+        )?;
+
+        return write_output(args, data.into_bytes());
+    }
+
+    // Before the renames below, while the names are still the slugs.
+    let counts = runs.map(|runs| {
+        nodes
+            .iter()
+            .map(|color| runs.get(&color.name).copied().unwrap_or(1))
+            .collect::<Vec<_>>()
+    });
+    let names = args
+        .names_from
+        .as_deref()
+        .map(|path| NameMap::load(path, args.verbose))
+        .transpose()?;
+    let mut remapped = 0;
+
+    // Lookups and reports above match on slugs, so only rename for the output.
+    for color in nodes.iter_mut() {
+        if let Some(name) = names.as_ref().and_then(|names| names.get(color)) {
+            color.name = name.to_string();
+            remapped += 1;
+        } else if args.name_case != NameCase::Slug {
+            let label = labels.get(&color.name).map(String::as_str);
+
+            color.name = args.name_case.apply(&color.name, label);
+        }
+    }
+
+    if args.verbose && names.is_some() {
+        eprintln!(
+            "--names-from renamed {} of {} colors",
+            remapped,
+            nodes.len()
+        );
+    }
+
+    if nodes.is_empty() && !args.allow_empty {
+        if args.strict {
+            anyhow::bail!("no colors are left to write; pass --allow-empty if that is intended");
+        }
+
+        // An empty page was already warned about.
+        if !args.quiet && !nothing_parsed {
+            eprintln!(
+                "warning: no colors are left to write; check --filter, --exclude and --min-distance"
+            );
+        }
+    }
+
+    if args.preview {
+        if dry_run(args, nodes.len(), "colors") {
+            return Ok(());
+        }
+
+        print!(
+            "{}",
+            generate_preview(
+                &nodes,
+                args.swatch_width,
+                args.ansi.unwrap_or_else(AnsiMode::detect)
+            )?
+        );
+
+        return Ok(());
+    }
+
+    if let Some(dir) = &args.output_dir {
+        return write_output_dir(args, &nodes, counts.as_deref(), dir);
+    }
+
+    if args.append {
+        return append_output(args, &nodes, counts.as_deref());
+    }
+
+    let data = match (&args.template, handlebars) {
+        (Some(template), _) => {
+            generate_template(&nodes, template, &args.header, &args.footer)?.into_bytes()
+        }
+        (_, Some(handlebars)) => generate_handlebars(&nodes, handlebars)?.into_bytes(),
+        _ => generate(args, &nodes, counts.as_deref(), args.format)?,
+    };
+
+    write_output(args, data)
+}
+
+/// Renders `nodes` as `format`, with a count column when `counts` holds the
+/// length of the run each color was collapsed from.
+fn generate(
+    args: &CommandLine,
+    nodes: &[Color],
+    counts: Option<&[usize]>,
+    format: OutputFormat,
+) -> anyhow::Result<Vec<u8>> {
+    let mut extras = ExtraColumns::compute(&Extra::from_args(args), nodes, args.jobs)?;
+
+    if let Some(counts) = counts {
+        extras.push_column(
+            "count",
+            counts.iter().map(|&count| Field::Int(count as i64)),
+        );
+    }
+
+    let scale = if args.linear {
+        ChannelScale::Linear
+    } else {
+        args.channel_scale
+    };
+    // Only the variable names get the affixes; html ids stay as they are.
+    let identifiers = || unique_identifiers(nodes, &args.name_prefix, &args.name_suffix, &[]);
+
+    Ok(match format {
+        OutputFormat::Json => {
+            generate_json(nodes, scale, args.channel_precision, args.group_by, &extras)?
+                .into_bytes()
+        }
+        OutputFormat::Xml => {
+            generate_xml(nodes, args.group_by, &extras, args.output_encoding)?.into_bytes()
+        }
+        OutputFormat::Csv => {
+            // Only --linear changes csv channels; --channel-scale is for json.
+            let scale = if args.linear {
+                ChannelScale::Linear
+            } else {
+                ChannelScale::Byte
+            };
+
+            generate_csv(nodes, scale, args.channel_precision, args.align, &extras)?.into_bytes()
+        }
+        OutputFormat::HexList => {
+            generate_hex_list(nodes, args.wrap_width, &args.separator)?.into_bytes()
+        }
+        OutputFormat::JascPal => generate_jasc_pal(nodes)?.into_bytes(),
+        OutputFormat::Gpl => {
+            generate_gpl(nodes, &args.palette_name, args.palette_columns)?.into_bytes()
+        }
+        OutputFormat::Less => generate_less(nodes, &identifiers())?.into_bytes(),
+        OutputFormat::Css => generate_css(
+            nodes,
+            &identifiers(),
+            args.css_themes,
+            args.css_color_syntax,
+            args.rounding,
+        )?
+        .into_bytes(),
+        OutputFormat::Sql => generate_sql(nodes, args.sql_dialect)?.into_bytes(),
+        OutputFormat::Html => generate_html(
+            nodes,
+            args.with_index,
+            args.group_by,
+            &extras,
+            args.output_encoding,
+        )?
+        .into_bytes(),
+        OutputFormat::Ase => generate_ase(nodes)?,
+        OutputFormat::Image => generate_image(
+            nodes,
+            args.swatch_size,
+            args.rows.unwrap_or(1),
+            args.label,
+            args.visual_order,
+        )?,
+        OutputFormat::Table => {
+            // Swatches are escape codes, so they're only drawn for a terminal.
+            let terminal = args.output.is_none()
+                && args.output_dir.is_none()
+                && !args.clipboard
+                && io::stdout().is_terminal();
+
+            generate_table(
+                nodes,
+                &args.table_columns,
+                args.table_width,
+                args.table_style,
+                terminal.then(AnsiMode::detect),
+            )?
+            .into_bytes()
+        }
+        OutputFormat::All => unreachable!("`all` is expanded before generating"),
+    })
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    file: String,
+    format: String,
+    size: usize,
+    sha256: String,
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    /// The pages or files the colors came from.
+    sources: Vec<String>,
+    /// When this run scraped or read the colors, as an RFC 3339 UTC timestamp.
+    scraped_at: String,
+    files: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    fn sources(args: &CommandLine) -> Vec<String> {
+        let path = |path: &PathBuf| path.display().to_string();
+
+        match &args.command {
+            Some(Command::Import { file })
+            | Some(Command::Diff {
+                new: Some(file), ..
+            }) => {
+                vec![path(file)]
+            }
+            Some(Command::Variants { .. }) => Vec::new(),
+            _ if !args.input.is_empty() => args.input.iter().map(path).collect(),
+            _ => args.url.clone(),
+        }
+    }
+}
+
+/// Writes one `colors.<ext>` file per format into `dir`, hashing each file as
+/// it is written so the manifest matches the bytes on disk.
+fn write_output_dir(
+    args: &CommandLine,
+    nodes: &[Color],
+    counts: Option<&[usize]>,
+    dir: &Path,
+) -> anyhow::Result<()> {
+    if dry_run(args, nodes.len(), "colors") {
+        return Ok(());
+    }
+
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let outputs = if args.split_channels {
+        split_channels(nodes, args.align)?
+            .into_iter()
+            .map(|(file, data)| Ok((file, OutputFormat::Csv, args.output_encoding.encode(data)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        args.format
+            .expand()
+            .into_iter()
+            .map(|format| {
+                let data = generate(args, nodes, counts, format)?;
+                let data = if format.is_binary() {
+                    data
+                } else {
+                    args.output_encoding.encode(data)?
+                };
+
+                Ok((output_file_name(format), format, data))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+    let mut files = Vec::new();
+
+    for (file, format, data) in outputs {
+        let path = dir.join(&file);
+
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            anyhow::bail!("interrupted before writing {}", path.display());
+        }
+
+        fs::write(&path, &data).with_context(|| format!("failed to write {}", path.display()))?;
+
+        if args.verbose {
+            eprintln!("wrote {} ({} bytes)", path.display(), data.len());
+        }
+
+        files.push(ManifestEntry {
+            file,
+            format: format.name(),
+            size: data.len(),
+            sha256: Sha256::digest(&data)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        });
+    }
+
+    if args.manifest {
+        let path = dir.join("manifest.json");
+        let manifest = serde_json::to_string_pretty(&Manifest {
+            sources: Manifest::sources(args),
+            scraped_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            files,
+        })?;
+
+        fs::write(&path, manifest + "\n")
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+fn output_file_name(format: OutputFormat) -> String {
+    format!("colors.{}", format.extension())
+}
+
+const CHANNEL_FILES: [&str; 3] = ["reds.csv", "greens.csv", "blues.csv"];
+
+/// Renders one CSV per channel, each with just the name and that channel.
+fn split_channels(nodes: &[Color], align: bool) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    CHANNEL_FILES
+        .iter()
+        .zip(["red", "green", "blue"])
+        .enumerate()
+        .map(|(index, (file, column))| {
+            let mut rows = vec![vec!["name".to_string(), column.to_string()]];
+
+            rows.extend(nodes.iter().map(|color| {
+                let channel = [color.red, color.green, color.blue][index];
+
+                vec![escape_csv(&color.name), channel.to_string()]
+            }));
+
+            Ok((file.to_string(), render_csv(rows, align)?.into_bytes()))
+        })
+        .collect()
+}
+
+/// Applies --filter and --exclude to a color's name.
+fn is_selected(args: &CommandLine, color: &Color) -> bool {
+    args.filter
+        .as_ref()
+        .is_none_or(|filter| filter.is_match(&color.name))
+        && !args
+            .exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.is_match(&color.name))
+}
+
+fn cache_dir(args: &CommandLine) -> anyhow::Result<PathBuf> {
+    if let Some(dir) = &args.cache_dir {
+        return Ok(dir.clone());
+    }
+
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("colors"))
+        .ok_or_else(|| anyhow::anyhow!("no cache directory could be found; use --cache-dir"))
+}
+
+/// Whether the colors can come from the last snapshot of --url instead of a
+/// scrape. Only lookups qualify, since they are often run many times in a row
+/// and every full scrape replaces the snapshot anyway. Options that check or
+/// cut short the parse need the page itself, so any of them means a scrape.
+fn uses_snapshot(args: &CommandLine) -> bool {
+    let checks_parse =
+        args.strict || args.require_rgb || args.report_collisions || args.max_nodes.is_some();
+
+    !args.refresh
+        && !checks_parse
+        && matches!(
+            args.command,
+            Some(Command::Get { .. })
+                | Some(Command::Nearest { .. })
+                | Some(Command::Matrix { .. })
+        )
+}
+
+/// Scrapes --url and snapshots the result, unless the scrape was cut short by
+/// --limit or --max-nodes and so doesn't hold the whole page.
+async fn fetch_and_save(
+    args: &CommandLine,
+    labels: &mut HashMap<String, String>,
+) -> anyhow::Result<Vec<Color>> {
+    let options = ParseOptions::from_args(args);
+    let client = http_client(args)?;
+    // join_all yields the results in the order of the futures, not the order
+    // they finish in, so the merge is the same on every run.
+    let pages = join_all(args.url.iter().map(|url| {
+        fetch_all_pages(
+            &client,
+            url,
+            options,
+            args.next_selector.as_ref(),
+            args.max_pages,
+        )
+    }))
+    .await
+    .into_iter()
+    .collect::<anyhow::Result<Vec<_>>>()
+    .map_err(|err| name_proxy(args, err))?;
+    let nodes = unlabel(merge_pages(pages), labels);
+    let partial = args.limit.is_some() || args.max_nodes.is_some();
+
+    if !args.no_cache && !partial {
+        // The scrape itself succeeded, so a cache problem shouldn't fail it.
+        if let Err(err) =
+            cache_dir(args).and_then(|dir| save_snapshot(&dir, &source_key(args), &nodes))
+        {
+            eprintln!("warning: failed to save a snapshot: {:#}", err);
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// Builds the client for every request of a run. reqwest already honors
+/// HTTPS_PROXY, HTTP_PROXY and NO_PROXY; --proxy overrides them all.
+fn http_client(args: &CommandLine) -> anyhow::Result<Client> {
+    let mut builder = Client::builder();
+
+    if let Some(proxy) = &args.proxy {
+        builder = builder.proxy(
+            Proxy::all(proxy).with_context(|| format!("{} is not a valid proxy URL", proxy))?,
+        );
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Names the proxy in a failure to connect, which would otherwise read as if
+/// the page itself were unreachable.
+fn name_proxy(args: &CommandLine, err: anyhow::Error) -> anyhow::Error {
+    let proxy = args.proxy.clone().or_else(|| {
+        [
+            "HTTPS_PROXY",
+            "https_proxy",
+            "HTTP_PROXY",
+            "http_proxy",
+            "ALL_PROXY",
+            "all_proxy",
+        ]
+        .iter()
+        .find_map(|key| env::var(key).ok().filter(|value| !value.is_empty()))
+    });
+    let connect = err.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(reqwest::Error::is_connect)
+    });
+
+    match proxy {
+        Some(proxy) if connect => err.context(format!(
+            "failed to connect through the proxy {}; check --proxy or HTTPS_PROXY",
+            proxy
+        )),
+        _ => err,
+    }
+}
+
+/// Whether the colors will be scraped from --url rather than read from a file
+/// or made up.
+fn scrapes(args: &CommandLine) -> bool {
+    !matches!(
+        args.command,
+        Some(Command::Import { .. })
+            | Some(Command::Diff { new: Some(_), .. })
+            | Some(Command::Variants { .. })
+    ) && args.input.is_empty()
+}
+
+/// Asks for the ETag or Last-Modified of every --url, failing with
+/// [`Unchanged`] when they all match the ones saved at the last scrape, and
+/// otherwise returning them to be saved once this scrape succeeds.
+async fn check_unchanged(args: &CommandLine) -> anyhow::Result<String> {
+    let client = http_client(args)?;
+    let validators = join_all(args.url.iter().map(|url| fetch_validator(&client, url)))
+        .await
+        .into_iter()
+        .zip(&args.url)
+        .map(|(validator, url)| match validator.map_err(|err| name_proxy(args, err))? {
+            Some(validator) => Ok(validator),
+            None => anyhow::bail!(
+                "{} sent neither an ETag nor a Last-Modified header, so --skip-if-unchanged can't tell whether it changed",
+                url
+            ),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .join("\n");
+    let path = validators_path(&cache_dir(args)?, &source_key(args));
+
+    if fs::read_to_string(&path).is_ok_and(|saved| saved == validators) {
+        if args.verbose {
+            eprintln!("{}", Unchanged);
+        }
+
+        return Err(Unchanged.into());
+    }
+
+    Ok(validators)
+}
+
+fn save_validators(args: &CommandLine, validators: &str) -> anyhow::Result<()> {
+    let dir = cache_dir(args)?;
+    let path = validators_path(&dir, &source_key(args));
+
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    fs::write(&path, validators).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Identifies the set of --url pages a snapshot was taken of; a single page is
+/// keyed by its URL alone.
+fn source_key(args: &CommandLine) -> String {
+    args.url.join(" ")
+}
+
+/// A short hash of `url`, so any URL can name a file.
+fn url_hash(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Each URL gets its own snapshot, named by a hash so any URL is a valid file name.
+fn snapshot_path(dir: &Path, url: &str) -> PathBuf {
+    dir.join(format!("snapshot-{}.json", url_hash(url)))
+}
+
+fn validators_path(dir: &Path, url: &str) -> PathBuf {
+    dir.join(format!("validators-{}.txt", url_hash(url)))
+}
+
+fn save_snapshot(dir: &Path, url: &str, nodes: &[Color]) -> anyhow::Result<()> {
+    let path = snapshot_path(dir, url);
+
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    fs::write(&path, serde_json::to_string(nodes)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn load_snapshot(dir: &Path, url: &str) -> anyhow::Result<Vec<Color>> {
+    let path = snapshot_path(dir, url);
+    let data = fs::read_to_string(&path).map_err(|_| {
+        anyhow::anyhow!(
+            "no previous scrape of {} is cached in {}; run colors once to take a snapshot",
+            url,
+            dir.display()
+        )
+    })?;
+
+    serde_json::from_str(&data).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Lists the colors added and changed in `current` relative to `previous`,
+/// followed by the colors that were removed, matching colors by name.
+fn diff_colors(previous: &[Color], current: &[Color]) -> Report {
+    let find = |nodes: &'_ [Color], name: &str| -> Option<String> {
+        nodes
+            .iter()
+            .find(|color| color.name == name)
+            .map(Color::hex)
+    };
+    let mut report = Report::new("change", &["change", "name", "old", "new"]);
+
+    for color in current {
+        match find(previous, &color.name) {
+            None => report.rows.push(vec![
+                Field::Text("added".to_string()),
+                Field::Text(color.name.clone()),
+                Field::Empty,
+                Field::Text(color.hex()),
+            ]),
+            Some(old) if old != color.hex() => report.rows.push(vec![
+                Field::Text("changed".to_string()),
+                Field::Text(color.name.clone()),
+                Field::Text(old),
+                Field::Text(color.hex()),
+            ]),
+            Some(_) => {}
+        }
+    }
+
+    for color in previous {
+        if find(current, &color.name).is_none() {
+            report.rows.push(vec![
+                Field::Text("removed".to_string()),
+                Field::Text(color.name.clone()),
+                Field::Text(color.hex()),
+                Field::Empty,
+            ]);
+        }
+    }
+
+    report
+}
+
+/// Scrapes `url` and, given a `next` selector, each page its next link leads
+/// to, up to `max_pages` pages in all. A link back to a page already scraped
+/// ends the chain rather than looping.
+async fn fetch_all_pages(
+    client: &Client,
+    url: &str,
+    options: ParseOptions,
+    next: Option<&Selector>,
+    max_pages: usize,
+) -> anyhow::Result<Vec<(String, Color)>> {
+    let (mut html, mut nodes) = fetch_colors(client, url, options).await?;
+    let Some(next) = next else {
+        return Ok(nodes);
+    };
+    let mut page = Url::parse(url).with_context(|| format!("{} is not a valid URL", url))?;
+    let mut visited = HashSet::from([page.clone()]);
+
+    while visited.len() < max_pages {
+        let Some(link) = next_page_link(&html, &page, next) else {
+            break;
+        };
+
+        if !visited.insert(link.clone()) {
+            eprintln!(
+                "warning: {} links back to {}, which was already scraped; stopping",
+                page, link
+            );
+
+            break;
+        }
+
+        let (next_html, next_nodes) = fetch_colors(client, link.as_str(), options).await?;
+
+        nodes.extend(next_nodes);
+        (html, page) = (next_html, link);
+    }
+
+    Ok(nodes)
+}
+
+/// Resolves the `href` of the first element matching `selector` against the
+/// page's own URL, ignoring links that aren't to another web page.
+fn next_page_link(html: &str, page: &Url, selector: &Selector) -> Option<Url> {
+    let document = Html::parse_document(html);
+    let href = document.select(selector).next()?.value().attr("href")?;
+    let mut link = page.join(href).ok()?;
+
+    link.set_fragment(None);
+
+    matches!(link.scheme(), "http" | "https").then_some(link)
+}
+
+/// Lists each color of `palette` whose RGB differs from the scraped color of
+/// the same name, and each that has no scraped color at all. Names are
+/// compared as slugs, so `Light Blue` in the palette matches `light_blue`.
+fn validate_palette(palette: &[Color], scraped: &[Color]) -> Report {
+    let scraped = scraped
+        .iter()
+        .map(|color| (color.name.as_str(), color))
+        .collect::<HashMap<_, _>>();
+    let mut report = Report::new("problem", &["problem", "name", "expected", "actual"]);
+
+    for color in palette {
+        match scraped.get(slugify(&color.name).as_str()) {
+            None => report.rows.push(vec![
+                Field::Text("unknown".to_string()),
+                Field::Text(color.name.clone()),
+                Field::Empty,
+                Field::Text(color.hex()),
+            ]),
+            Some(expected) if expected.to_u32() != color.to_u32() => report.rows.push(vec![
+                Field::Text("mismatch".to_string()),
+                Field::Text(color.name.clone()),
+                Field::Text(expected.hex()),
+                Field::Text(color.hex()),
+            ]),
+            Some(_) => {}
+        }
+    }
+
+    report
+}
+
+/// Downloads and parses one page, returning its markup along with its colors.
+/// It retries once if the page parses to nothing, since a transient error page
+/// is far more likely than the list disappearing.
+async fn fetch_colors(
+    client: &Client,
+    url: &str,
+    options: ParseOptions,
+) -> anyhow::Result<(String, Vec<(String, Color)>)> {
+    let html = fetch_page_with(client, url, options.progress).await?;
+    let nodes = parse_html(&html, options)?;
+
+    if !nodes.is_empty() {
+        return Ok((html, nodes));
+    }
+
+    eprintln!("warning: no colors were parsed from {}; retrying once", url);
+
+    let html = fetch_page_with(client, url, options.progress).await?;
+    let nodes = parse_html(&html, options)?;
+
+    if nodes.is_empty() {
+        anyhow::bail!(
+            "no colors were parsed from {} after retrying; the server may be returning an error page or the page layout may have changed",
+            url
+        );
+    }
+
+    Ok((html, nodes))
+}
+
+/// A small copy of the page's markup with one of each kind of swatch, in both
+/// name/swatch orders.
+const SELF_TEST_PAGE: &str = include_str!("fixtures/self-test.html");
+
+/// The colors [`SELF_TEST_PAGE`] should parse to, in page order.
+const SELF_TEST_COLORS: [(&str, u8, u8, u8); 4] = [
+    ("light_blue", 173, 216, 230),
+    ("red", 255, 0, 0),
+    ("dark_gray_x11", 169, 169, 169),
+    ("office_green", 0, 128, 0),
+];
+
+/// Parses the bundled page and writes it in every format with the default
+/// options, failing on the first result that isn't what it should be.
+fn self_test() -> anyhow::Result<()> {
+    let channels = |colors: &[Color]| {
+        colors
+            .iter()
+            .map(|color| (color.name.clone(), color.red, color.green, color.blue))
+            .collect::<Vec<_>>()
+    };
+    let expected = SELF_TEST_COLORS
+        .iter()
+        .map(|&(name, red, green, blue)| (name.to_string(), red, green, blue))
+        .collect::<Vec<_>>();
+
+    let nodes = parse_colors(SELF_TEST_PAGE);
+
+    if channels(&nodes) != expected {
+        anyhow::bail!(
+            "parsed {:?} from the bundled page, expected {:?}",
+            channels(&nodes),
+            expected
+        );
+    }
+
+    println!("ok: parsed {} colors", nodes.len());
+
+    let args = CommandLine::parse_from(["colors"]);
+
+    for format in OutputFormat::All.expand() {
+        let data = generate(&args, &nodes, None, format)
+            .with_context(|| format!("failed to generate {}", format.name()))?;
+
+        // The formats that can be imported again must give back the same colors.
+        let round_trip = match format {
+            OutputFormat::Json => Some(serde_json::from_slice::<Vec<Color>>(&data)?),
+            OutputFormat::Csv => Some(
+                parse_csv(std::str::from_utf8(&data)?)?
+                    .into_iter()
+                    .map(|color| color.validate(true))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            _ => None,
+        };
+
+        if data.is_empty() {
+            anyhow::bail!("the {} output is empty", format.name());
+        }
+
+        if let Some(colors) = round_trip.filter(|colors| channels(colors) != expected) {
+            anyhow::bail!(
+                "the {} output reads back as {:?}, expected {:?}",
+                format.name(),
+                channels(&colors),
+                expected
+            );
+        }
+
+        println!("ok: {} ({} bytes)", format.name(), data.len());
+    }
+
+    Ok(())
+}
+
+/// Reads the inputs, or the pages as last downloaded for a benchmark (fetching
+/// any not yet cached, or all of them with --refresh), then times `passes`
+/// parses of them without any of the usual output.
+async fn bench_parse(args: &CommandLine, passes: u32) -> anyhow::Result<()> {
+    let pages = if args.input.is_empty() {
+        let client = http_client(args)?;
+        let dir = cache_dir(args)?;
+
+        join_all(args.url.iter().map(|url| {
+            let path = dir.join(format!("page-{}.html", url_hash(url)));
+            let (client, dir) = (&client, &dir);
+
+            async move {
+                if !args.refresh {
+                    if let Ok(html) = fs::read_to_string(&path) {
+                        return Ok(html);
+                    }
+                }
+
+                let html = fetch_page_with(client, url, args.progress_format).await?;
+
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("failed to create {}", dir.display()))?;
+                fs::write(&path, &html)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+
+                Ok(html)
+            }
+        }))
+        .await
+        .into_iter()
+        .collect::<anyhow::Result<Vec<_>>>()
+        .map_err(|err| name_proxy(args, err))?
+    } else {
+        args.input
+            .iter()
+            .map(|path| read_text(path, args.verbose))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    let mut colors = 0;
+    let mut times = Vec::with_capacity(passes as usize);
+
+    for _ in 0..passes {
+        let start = Instant::now();
+
+        colors = pages.iter().map(|html| parse_page(html).colors.len()).sum();
+        times.push(start.elapsed());
+    }
+
+    times.sort();
+
+    let median = match times.len() {
+        len if len % 2 == 0 => (times[len / 2 - 1] + times[len / 2]) / 2,
+        len => times[len / 2],
+    };
+
+    eprintln!(
+        "parsed {} colors {} times: {:?} mean, {:?} median per pass",
+        colors,
+        passes,
+        times.iter().sum::<std::time::Duration>() / passes,
+        median
+    );
+
+    Ok(())
+}
+
+/// How the markup of a page is turned into colors.
+#[derive(Debug, Copy, Clone)]
+struct ParseOptions {
+    report_collisions: bool,
+    strict: bool,
+    require_rgb: bool,
+    limits: ParseLimits,
+    progress: Progress,
+}
+
+impl ParseOptions {
+    fn from_args(args: &CommandLine) -> Self {
+        ParseOptions {
+            report_collisions: args.report_collisions,
+            strict: args.strict,
+            require_rgb: args.require_rgb,
+            progress: args.progress_format,
+            limits: ParseLimits {
+                max_nodes: args.max_nodes,
+                // Reading one color past --max-colors is enough to fail on.
+                max_colors: Some(
+                    args.limit
+                        .unwrap_or(usize::MAX)
+                        .min(args.max_colors.saturating_add(1)),
+                ),
+            },
+        }
+    }
+}
+
+fn parse_opacity(opacity: &str) -> anyhow::Result<f64> {
+    let opacity = opacity.parse::<f64>()?;
+
+    if !(0.0..=1.0).contains(&opacity) {
+        anyhow::bail!("{} is not between 0 and 1", opacity);
+    }
+
+    Ok(opacity)
+}
+
+fn parse_selector(selector: &str) -> anyhow::Result<Selector> {
+    Selector::parse(selector).map_err(|err| anyhow::anyhow!("invalid CSS selector: {}", err))
+}
+
+fn parse_html(html: &str, options: ParseOptions) -> anyhow::Result<Vec<(String, Color)>> {
+    let page = parse_page_limited(html, options.limits);
+
+    if options.require_rgb && !page.missing_rgb.is_empty() {
+        anyhow::bail!(
+            "{} names have no RGB value: {}",
+            page.missing_rgb.len(),
+            page.missing_rgb
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    if options.strict && !page.warnings.is_empty() {
+        anyhow::bail!(
+            "the page doesn't have the expected layout:\n  {}",
+            page.warnings.join("\n  ")
+        );
+    }
+
+    for warning in page.warnings.iter() {
+        match options.progress {
+            Progress::Bar => eprintln!("warning: {}", warning),
+            Progress::Json => eprintln!(
+                "{}",
+                serde_json::json!({ "phase": "parse", "warning": warning })
+            ),
+        }
+    }
+
+    let labeled = page.colors;
+
+    if options.report_collisions {
+        for (slug, colors) in find_collisions(&labeled) {
+            eprintln!(
+                "warning: `{}` is the slug of {}",
+                slug,
+                colors
+                    .iter()
+                    .map(|(label, name)| format!("\"{}\" (kept as `{}`)", label, name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
+
+    if let Progress::Json = options.progress {
+        eprintln!(
+            "{}",
+            serde_json::json!({ "phase": "parse", "colors": labeled.len() })
+        );
+    }
+
+    Ok(labeled)
+}
+
+/// Splits page colors from their page names, recording each name by slug.
+fn unlabel(labeled: Vec<(String, Color)>, labels: &mut HashMap<String, String>) -> Vec<Color> {
+    labeled
+        .into_iter()
+        .map(|(label, color)| {
+            labels.insert(color.name.clone(), label);
+            color
+        })
+        .collect()
+}
+
+/// A slug and the `(page name, kept name)` of each color that shared it.
+type Collision<'a> = (String, Vec<(&'a str, &'a str)>);
+
+/// Finds the slugs shared by more than one distinct page name, in page order,
+/// along with the suffixed name each of those colors was given instead.
+fn find_collisions(labeled: &[(String, Color)]) -> Vec<Collision<'_>> {
+    let mut slugs: Vec<Collision> = Vec::new();
+
+    for (label, color) in labeled {
+        let slug = slugify(label);
+        let entry = (label.as_str(), color.name.as_str());
+
+        match slugs.iter_mut().find(|(other, _)| *other == slug) {
+            Some((_, colors)) if !colors.iter().any(|(other, _)| other == label) => {
+                colors.push(entry)
+            }
+            Some(_) => {}
+            None => slugs.push((slug, vec![entry])),
+        }
+    }
+
+    slugs.retain(|(_, colors)| colors.len() > 1);
+    slugs
+}
+
+/// Under --dry-run, reports how much would have gone to the destination and
+/// returns true so the caller writes nothing.
+fn dry_run(args: &CommandLine, amount: usize, unit: &str) -> bool {
+    if args.dry_run {
+        println!(
+            "would write {} {} as {}",
+            amount,
+            unit,
+            describe_destination(args)
+        );
+    }
+
+    args.dry_run
+}
+
+fn write_output(args: &CommandLine, data: Vec<u8>) -> anyhow::Result<()> {
+    if dry_run(args, data.len(), "bytes") {
+        return Ok(());
+    }
+
+    if let Some(path) = &args.output {
+        let data = args.output_encoding.encode(data)?;
+
+        fs::write(path, data).with_context(|| format!("failed to write {}", path.display()))?;
+    } else if args.clipboard {
+        copy_to_clipboard(String::from_utf8(data)?)?;
+    } else if args.output_encoding != OutputEncoding::Utf8 {
+        let mut data = data;
+
+        // The newline println! would add, encoded with the rest.
+        data.push(b'\n');
+        io::stdout().write_all(&args.output_encoding.encode(data)?)?;
+    } else {
+        println!("{}", String::from_utf8(data)?);
+    }
+
+    Ok(())
+}
+
+/// Appends the rows that aren't already in --output, keyed by name for csv
+/// and by hex code for hex-list, writing the csv header only to a new file.
+fn append_output(
+    args: &CommandLine,
+    nodes: &[Color],
+    counts: Option<&[usize]>,
+) -> anyhow::Result<()> {
+    if dry_run(args, nodes.len(), "colors") {
+        return Ok(());
+    }
+
+    let path = args.output.as_deref().expect("--append requires --output");
+    let has_header = matches!(args.format, OutputFormat::Csv);
+    let key = |line: &str| line.split(',').next().unwrap_or_default().to_string();
+    let existing = match fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let mut known = existing
+        .lines()
+        .skip(has_header as usize)
+        .filter(|line| !line.is_empty())
+        .map(key)
+        .collect::<HashSet<_>>();
+
+    let generated = String::from_utf8(generate(args, nodes, counts, args.format)?)?;
+    let mut lines = generated.lines();
+    let mut buf = String::new();
+
+    if has_header {
+        let header = lines.next().unwrap_or_default();
+
+        if existing.trim().is_empty() {
+            writeln!(buf, "{}", header)?;
+        }
+    }
+
+    for line in lines {
+        if known.insert(key(line)) {
+            writeln!(buf, "{}", line)?;
+        }
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+
+    io::Write::write_all(&mut file, buf.as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Reads the lookup queries from the positional argument or one per line of
+/// stdin, skipping nothing so every line gets a result row.
+fn read_queries(query: &Option<String>, stdin: bool) -> anyhow::Result<Vec<String>> {
+    if stdin {
+        Ok(io::stdin().lines().collect::<Result<_, _>>()?)
+    } else {
+        Ok(query.iter().cloned().collect())
+    }
+}
+
+fn lookup_names(nodes: &[Color], query: &Option<String>, stdin: bool) -> anyhow::Result<Report> {
+    let mut report = Report::new(
+        "result",
+        &["query", "name", "red", "green", "blue", "error"],
+    );
+
+    for query in read_queries(query, stdin)? {
+        let name = slugify(query.trim());
+        let color = nodes.iter().find(|color| color.name == name);
+        let row = match color {
+            _ if name.is_empty() => vec![
+                Field::Text(query),
+                Field::Empty,
+                Field::Empty,
+                Field::Empty,
+                Field::Empty,
+                Field::Text("empty query".to_string()),
+            ],
+            Some(color) => vec![
+                Field::Text(query),
+                Field::Text(color.name.clone()),
+                Field::Int(color.red as i64),
+                Field::Int(color.green as i64),
+                Field::Int(color.blue as i64),
+                Field::Empty,
+            ],
+            None => vec![
+                Field::Text(query),
+                Field::Empty,
+                Field::Empty,
+                Field::Empty,
+                Field::Empty,
+                Field::Text(format!("no color named `{}`", name)),
+            ],
+        };
+
+        report.rows.push(row);
+    }
+
+    Ok(report)
+}
+
+fn lookup_nearest(
+    nodes: &[Color],
+    query: &Option<String>,
+    stdin: bool,
+    metric: DistanceMetric,
+) -> anyhow::Result<Report> {
+    let mut report = Report::new(
+        "result",
+        &["query", "name", "red", "green", "blue", "distance", "error"],
+    );
+
+    for query in read_queries(query, stdin)? {
+        let nearest = parse_color_input(&query).and_then(|(red, green, blue, _)| {
+            let target = Color {
+                name: String::new(),
+                red,
+                green,
+                blue,
+            };
+
+            nodes
+                .iter()
+                .map(|color| (color, metric.distance(color, &target)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .ok_or_else(|| anyhow::anyhow!("no colors to match against"))
+        });
+        let row = match nearest {
+            Ok((color, distance)) => vec![
+                Field::Text(query),
+                Field::Text(color.name.clone()),
+                Field::Int(color.red as i64),
+                Field::Int(color.green as i64),
+                Field::Int(color.blue as i64),
+                Field::Float(distance),
+                Field::Empty,
+            ],
+            Err(err) => vec![
+                Field::Text(query),
+                Field::Empty,
+                Field::Empty,
+                Field::Empty,
+                Field::Empty,
+                Field::Empty,
+                Field::Text(err.to_string()),
+            ],
+        };
+
+        report.rows.push(row);
+    }
+
+    Ok(report)
+}
+
+/// Picks the named colors in the given order, or every color when no names
+/// are given.
+fn select_colors<'a>(nodes: &'a [Color], names: &[String]) -> anyhow::Result<Vec<&'a Color>> {
+    if names.is_empty() {
+        return Ok(nodes.iter().collect());
+    }
+
+    names
+        .iter()
+        .map(|name| {
+            let name = slugify(name);
+
+            nodes
+                .iter()
+                .find(|color| color.name == name)
+                .ok_or_else(|| anyhow::anyhow!("no color named `{}`", name))
+        })
+        .collect()
+}
+
+fn describe_destination(args: &CommandLine) -> String {
+    if args.preview {
+        return "a preview to the terminal".to_string();
+    }
+
+    let format = if args.template.is_some() || args.template_file.is_some() {
+        "templated text".to_string()
+    } else {
+        args.format.name()
+    };
+
+    if let Some(dir) = &args.output_dir {
+        let mut files = if args.split_channels {
+            CHANNEL_FILES.map(ToString::to_string).to_vec()
+        } else {
+            args.format
+                .expand()
+                .into_iter()
+                .map(output_file_name)
+                .collect()
+        };
+
+        if args.manifest {
+            files.push("manifest.json".to_string());
+        }
+
+        format!("{} to {} in {}", format, files.join(", "), dir.display())
+    } else if let Some(path) = &args.output {
+        format!("{} to {}", format, path.display())
+    } else if args.clipboard {
+        format!("{} to the clipboard", format)
+    } else {
+        format!("{} to stdout", format)
+    }
+}
+
+#[cfg(not(test))]
+fn copy_to_clipboard(data: String) -> anyhow::Result<()> {
+    let mut clipboard = arboard::Clipboard::new().context("no system clipboard is available")?;
+
+    clipboard
+        .set_text(data)
+        .context("failed to copy the output to the clipboard")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Stands in for the system clipboard, which test machines rarely have.
+    static CLIPBOARD: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(test)]
+fn copy_to_clipboard(data: String) -> anyhow::Result<()> {
+    CLIPBOARD.with(|clipboard| *clipboard.borrow_mut() = Some(data));
+
+    Ok(())
+}
+
+fn sort_colors(
+    nodes: &mut Vec<Color>,
+    key: SortKey,
+    hue_start: f64,
+    path_start: Option<(u8, u8, u8, u8)>,
+) {
+    match key {
+        SortKey::Name => nodes.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortKey::Luminance => nodes.sort_by(|a, b| a.luma().total_cmp(&b.luma())),
+        SortKey::Lightness => nodes.sort_by(|a, b| a.to_lab().0.total_cmp(&b.to_lab().0)),
+        SortKey::Hue => nodes.sort_by(|a, b| {
+            // Grays have no hue, so they go last, dark to light, then by name.
+            let key = |color: &Color| {
+                let (hue, saturation, lightness) = color.to_hsl();
+                let gray = saturation == 0.0;
+                let hue = if gray {
+                    0.0
+                } else {
+                    (hue - hue_start).rem_euclid(360.0)
+                };
+
+                (gray, hue, lightness)
+            };
+            let (a_gray, a_hue, a_lightness) = key(a);
+            let (b_gray, b_hue, b_lightness) = key(b);
+
+            a_gray
+                .cmp(&b_gray)
+                .then(a_hue.total_cmp(&b_hue))
+                .then(a_lightness.total_cmp(&b_lightness))
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        SortKey::Saturation | SortKey::Value => {
+            let rank = |color: &Color| {
+                let (_, saturation, value) = color.to_hsv();
+                let primary = match key {
+                    SortKey::Saturation => saturation,
+                    _ => value,
+                };
+
+                (primary, color.to_hsl().2)
+            };
+
+            nodes.sort_by(|a, b| {
+                let (a_key, a_lightness) = rank(a);
+                let (b_key, b_lightness) = rank(b);
+
+                a_key
+                    .total_cmp(&b_key)
+                    .then(a_lightness.total_cmp(&b_lightness))
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
+        SortKey::Popularity => {
+            // Each channel is split into 8 bands, giving 512 cubes of RGB space;
+            // a color's popularity is how many loaded colors share its cube.
+            const BAND: u8 = 32;
+
+            let bucket = |color: &Color| (color.red / BAND, color.green / BAND, color.blue / BAND);
+            let mut counts = HashMap::<_, usize>::new();
+
+            for color in nodes.iter() {
+                *counts.entry(bucket(color)).or_default() += 1;
+            }
+
+            nodes.sort_by(|a, b| {
+                counts[&bucket(b)]
+                    .cmp(&counts[&bucket(a)])
+                    .then_with(|| bucket(a).cmp(&bucket(b)))
+                    .then_with(|| a.name.cmp(&b.name))
+            });
+        }
+        SortKey::Path => {
+            let start = path_start.map(|(red, green, blue, _)| Color {
+                name: String::new(),
+                red,
+                green,
+                blue,
+            });
+            let order = chain_by_similarity(nodes, start.as_ref());
+            let mut slots = nodes.drain(..).map(Some).collect::<Vec<_>>();
+
+            nodes.extend(order.into_iter().filter_map(|index| slots[index].take()));
+        }
+    }
+}
+
+/// Linearly maps each channel's observed range onto 0-255, leaving a channel
+/// alone when every color has the same value for it.
+fn stretch_channels(nodes: &mut [Color], rounding: Rounding) {
+    let channels: [fn(&mut Color) -> &mut u8; 3] = [
+        |color| &mut color.red,
+        |color| &mut color.green,
+        |color| &mut color.blue,
+    ];
+
+    for channel in channels {
+        let values = nodes.iter_mut().map(|color| *channel(color));
+        let (low, high) = values.fold((u8::MAX, u8::MIN), |(low, high), value| {
+            (low.min(value), high.max(value))
+        });
+
+        if low >= high {
+            continue;
+        }
+
+        for color in nodes.iter_mut() {
+            let value = channel(color);
+            let scaled = (*value - low) as f64 * 255.0 / (high - low) as f64;
+
+            *value = to_u8(scaled, rounding);
+        }
+    }
+}
+
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => rand::make_rng(),
+    }
+}
+
+/// Keeps up to `per_family` random colors from each hue family, leaving the
+/// kept colors in their original order.
+fn sample_per_family(nodes: Vec<Color>, per_family: usize, rng: &mut StdRng) -> Vec<Color> {
+    let mut keep = vec![false; nodes.len()];
+
+    for &family in HUE_FAMILIES {
+        let mut members = (0..nodes.len())
+            .filter(|&index| nodes[index].hue_family() == family)
+            .collect::<Vec<_>>();
+
+        members.shuffle(rng);
+
+        for &index in members.iter().take(per_family) {
+            keep[index] = true;
+        }
+    }
+
+    nodes
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(color, keep)| keep.then_some(color))
+        .collect()
+}
+
+/// Replaces each color's lightness with its rank among all the colors, spread
+/// evenly over `0.0..=1.0`. Each rank takes the middle of its share of the
+/// range, so no color collapses to pure black or white and loses its hue.
+/// Ties rank by name so the result doesn't depend on the input order.
+fn equalize_lightness(nodes: &[Color], rounding: Rounding) -> Vec<Color> {
+    let mut order = (0..nodes.len()).collect::<Vec<_>>();
+
+    order.sort_by(|&a, &b| {
+        nodes[a]
+            .to_hsl()
+            .2
+            .total_cmp(&nodes[b].to_hsl().2)
+            .then_with(|| nodes[a].name.cmp(&nodes[b].name))
+    });
+
+    let mut lightness = vec![0.0; nodes.len()];
+
+    for (rank, &index) in order.iter().enumerate() {
+        lightness[index] = (rank as f64 + 0.5) / nodes.len() as f64;
+    }
+
+    nodes
+        .iter()
+        .zip(lightness)
+        .map(|(color, lightness)| {
+            let (hue, saturation, _) = color.to_hsl();
+
+            Color::from_hsl(color.name.clone(), hue, saturation, lightness, rounding)
+        })
+        .collect()
+}
+
+/// Blends `base` toward white for each tint and toward black for each shade,
+/// in even steps that stop short of pure white and black.
+fn variants(base: &Color, steps: u32, rounding: Rounding) -> Vec<Color> {
+    let white = Color {
+        name: String::new(),
+        red: 255,
+        green: 255,
+        blue: 255,
+    };
+    let black = Color {
+        name: String::new(),
+        red: 0,
+        green: 0,
+        blue: 0,
+    };
+    let ramp = |kind: &str, target: &Color| {
+        (1..=steps)
+            .map(|step| {
+                let weight = 1.0 - step as f64 / (steps + 1) as f64;
+                let mut color = base.blend_over(target, weight, rounding);
+
+                color.name = format!("{}_{}_{}", base.name, kind, step);
+                color
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut colors = ramp("tint", &white);
+
+    colors.extend(ramp("shade", &black));
+    colors
+}
+
+/// Greedily keeps colors in order, dropping any within `min_distance` of one
+/// that was already kept.
+fn thin_colors(nodes: Vec<Color>, min_distance: f64, metric: DistanceMetric) -> Vec<Color> {
+    let mut kept: Vec<Color> = Vec::with_capacity(nodes.len());
+
+    for color in nodes {
+        if kept
+            .iter()
+            .all(|other| metric.distance(&color, other) >= min_distance)
+        {
+            kept.push(color);
+        }
+    }
+
+    kept
+}
+
+/// Renames the closest scraped color for every entry of the preset's palette.
+fn apply_preset(nodes: &[Color], preset: Preset, rounding: Rounding) -> Vec<Color> {
+    preset_targets(preset, rounding)
+        .into_iter()
+        .filter_map(|target| {
+            let nearest = nodes.iter().min_by(|a, b| {
+                a.distance(&target)
+                    .partial_cmp(&b.distance(&target))
+                    .unwrap()
+            })?;
+
+            Some(Color {
+                name: target.name,
+                red: nearest.red,
+                green: nearest.green,
+                blue: nearest.blue,
+            })
+        })
+        .collect()
+}
+
+fn preset_targets(preset: Preset, rounding: Rounding) -> Vec<Color> {
+    // (family, hue, saturation)
+    const MATERIAL_HUES: &[(&str, f64, f64)] = &[
+        ("red", 4.0, 0.9),
+        ("pink", 340.0, 0.82),
+        ("purple", 291.0, 0.64),
+        ("deep_purple", 262.0, 0.52),
+        ("indigo", 231.0, 0.48),
+        ("blue", 207.0, 0.9),
+        ("light_blue", 199.0, 0.98),
+        ("cyan", 187.0, 1.0),
+        ("teal", 174.0, 1.0),
+        ("green", 122.0, 0.39),
+        ("light_green", 88.0, 0.5),
+        ("lime", 66.0, 0.7),
+        ("yellow", 54.0, 1.0),
+        ("amber", 45.0, 1.0),
+        ("orange", 36.0, 1.0),
+        ("deep_orange", 14.0, 1.0),
+        ("brown", 16.0, 0.25),
+        ("grey", 0.0, 0.0),
+        ("blue_grey", 200.0, 0.18),
+    ];
+    // (shade, lightness)
+    const MATERIAL_SHADES: &[(u16, f64)] = &[
+        (50, 0.95),
+        (100, 0.9),
+        (200, 0.8),
+        (300, 0.7),
+        (400, 0.6),
+        (500, 0.5),
+        (600, 0.42),
+        (700, 0.35),
+        (800, 0.27),
+        (900, 0.2),
+    ];
+    const TAILWIND_HUES: &[(&str, f64, f64)] = &[
+        ("slate", 215.0, 0.16),
+        ("gray", 220.0, 0.09),
+        ("zinc", 240.0, 0.05),
+        ("neutral", 0.0, 0.0),
+        ("stone", 25.0, 0.06),
+        ("red", 0.0, 0.84),
+        ("orange", 25.0, 0.95),
+        ("amber", 38.0, 0.92),
+        ("yellow", 48.0, 0.96),
+        ("lime", 84.0, 0.81),
+        ("green", 142.0, 0.71),
+        ("emerald", 160.0, 0.84),
+        ("teal", 173.0, 0.8),
+        ("cyan", 189.0, 0.94),
+        ("sky", 199.0, 0.89),
+        ("blue", 217.0, 0.91),
+        ("indigo", 239.0, 0.84),
+        ("violet", 258.0, 0.9),
+        ("purple", 271.0, 0.91),
+        ("fuchsia", 292.0, 0.84),
+        ("pink", 330.0, 0.81),
+        ("rose", 350.0, 0.89),
+    ];
+    const TAILWIND_SHADES: &[(u16, f64)] = &[
+        (50, 0.97),
+        (100, 0.94),
+        (200, 0.86),
+        (300, 0.76),
+        (400, 0.64),
+        (500, 0.53),
+        (600, 0.45),
+        (700, 0.38),
+        (800, 0.31),
+        (900, 0.25),
+        (950, 0.15),
+    ];
+    const WEB: &[(&str, u8, u8, u8)] = &[
+        ("black", 0, 0, 0),
+        ("silver", 192, 192, 192),
+        ("gray", 128, 128, 128),
+        ("white", 255, 255, 255),
+        ("maroon", 128, 0, 0),
+        ("red", 255, 0, 0),
+        ("purple", 128, 0, 128),
+        ("fuchsia", 255, 0, 255),
+        ("green", 0, 128, 0),
+        ("lime", 0, 255, 0),
+        ("olive", 128, 128, 0),
+        ("yellow", 255, 255, 0),
+        ("navy", 0, 0, 128),
+        ("blue", 0, 0, 255),
+        ("teal", 0, 128, 128),
+        ("aqua", 0, 255, 255),
+    ];
+
+    let ramps = move |hues: &[(&str, f64, f64)], shades: &[(u16, f64)]| {
+        hues.iter()
+            .flat_map(|&(family, hue, saturation)| {
+                shades.iter().map(move |&(shade, lightness)| {
+                    Color::from_hsl(
+                        format!("{}_{}", family, shade),
+                        hue,
+                        saturation,
+                        lightness,
+                        rounding,
+                    )
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+
+    match preset {
+        Preset::Material => ramps(MATERIAL_HUES, MATERIAL_SHADES),
+        Preset::Tailwind => ramps(TAILWIND_HUES, TAILWIND_SHADES),
+        Preset::Web => WEB
+            .iter()
+            .map(|&(name, red, green, blue)| Color {
+                name: name.to_string(),
+                red,
+                green,
+                blue,
+            })
+            .collect(),
+    }
+}
+
+/// Pairs up every two colors whose Delta-E is at most `threshold`, closest
+/// pairs first.
+fn find_near_duplicates(nodes: &[Color], threshold: f64) -> Report {
+    let mut pairs = Vec::new();
+
+    for (index, first) in nodes.iter().enumerate() {
+        for second in &nodes[index + 1..] {
+            let delta_e = first.delta_e(second);
+
+            if delta_e <= threshold {
+                pairs.push((first, second, delta_e));
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let mut report = Report::new(
+        "pair",
+        &["first", "first_hex", "second", "second_hex", "delta_e"],
+    );
+
+    report.rows = pairs
+        .into_iter()
+        .map(|(first, second, delta_e)| {
+            vec![
+                Field::Text(first.name.clone()),
+                Field::Text(first.hex()),
+                Field::Text(second.name.clone()),
+                Field::Text(second.hex()),
+                Field::Float(delta_e),
+            ]
+        })
+        .collect();
+
+    report
+}
+
+/// Describes every concrete output format, straight from [`OutputFormat`].
+fn list_formats() -> Report {
+    let mut report = Report::new(
+        "format",
+        &[
+            "name",
+            "extension",
+            "binary",
+            "requires_output",
+            "description",
+        ],
+    );
+
+    report.rows = OutputFormat::value_variants()
+        .iter()
+        .map(|&format| {
+            let description = format
+                .to_possible_value()
+                .and_then(|value| value.get_help().map(ToString::to_string));
+            // `all` has no extension of its own; it writes every other format.
+            let extension = match format {
+                OutputFormat::All => Field::Empty,
+                format => Field::Text(format.extension().to_string()),
+            };
+
+            vec![
+                Field::Text(format.name()),
+                extension,
+                Field::Bool(format.is_binary()),
+                format
+                    .required_output()
+                    .map_or(Field::Empty, |option| Field::Text(option.to_string())),
+                description.map_or(Field::Empty, Field::Text),
+            ]
+        })
+        .collect();
+
+    report
+}
+
+/// A single cell of a [`Report`].
+#[derive(Debug)]
+enum Field {
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Empty,
+}
+
+impl Field {
+    fn json(&self) -> String {
+        match self {
+            Field::Text(text) => serde_json::to_string(text).unwrap(),
+            Field::Int(value) => value.to_string(),
+            Field::Float(value) => format!("{:.3}", value),
+            Field::Bool(value) => value.to_string(),
+            Field::Empty => "null".to_string(),
+        }
+    }
+
+    fn xml(&self) -> String {
+        match self {
+            Field::Text(text) => escape_xml(text),
+            Field::Int(value) => value.to_string(),
+            Field::Float(value) => format!("{:.3}", value),
+            Field::Bool(value) => value.to_string(),
+            Field::Empty => String::new(),
+        }
+    }
+
+    fn csv(&self) -> String {
+        match self {
+            Field::Text(text) => escape_csv(text),
+            Field::Int(value) => value.to_string(),
+            Field::Float(value) => format!("{:.3}", value),
+            Field::Bool(value) => value.to_string(),
+            Field::Empty => String::new(),
+        }
+    }
+}
+
+/// A computed column appended after the channels in the json, xml and csv
+/// color formats.
+#[derive(Debug, Copy, Clone)]
+enum Extra {
+    TextColor,
+    Int(IntFormat),
+}
+
+impl Extra {
+    fn from_args(args: &CommandLine) -> Vec<Extra> {
+        let mut extras = Vec::new();
+
+        if args.include_text_color {
+            extras.push(Extra::TextColor);
+        }
+
+        if args.with_int {
+            extras.push(Extra::Int(args.int_format));
+        }
+
+        extras
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Extra::TextColor => "text_color",
+            Extra::Int(_) => "int",
+        }
+    }
+
+    fn value(self, color: &Color) -> Field {
+        match self {
+            Extra::TextColor => Field::Text(color.best_text_color().0.name),
+            Extra::Int(IntFormat::Dec) => Field::Int(color.to_u32() as i64),
+            Extra::Int(IntFormat::Hex) => Field::Text(format!("0x{:06x}", color.to_u32())),
+        }
+    }
+}
+
+/// The computed columns of every color, worked out before rendering so the
+/// conversions can be spread over --jobs threads.
+struct ExtraColumns {
+    names: Vec<&'static str>,
+    rows: Vec<Vec<Field>>,
+}
+
+impl ExtraColumns {
+    fn compute(extras: &[Extra], nodes: &[Color], jobs: usize) -> anyhow::Result<Self> {
+        let row = |color: &Color| {
+            extras
+                .iter()
+                .map(|extra| extra.value(color))
+                .collect::<Vec<_>>()
+        };
+        // Collecting an indexed parallel iterator keeps the colors in order.
+        let rows = if extras.is_empty() || jobs == 1 {
+            nodes.iter().map(row).collect()
+        } else {
+            ThreadPoolBuilder::new()
+                .num_threads(jobs)
+                .build()?
+                .install(|| nodes.par_iter().map(row).collect())
+        };
+
+        Ok(ExtraColumns {
+            names: extras.iter().map(|extra| extra.name()).collect(),
+            rows,
+        })
+    }
+
+    /// Adds a column worked out elsewhere, with one field per color.
+    fn push_column(&mut self, name: &'static str, fields: impl IntoIterator<Item = Field>) {
+        self.names.push(name);
+
+        for (row, field) in self.rows.iter_mut().zip(fields) {
+            row.push(field);
+        }
+    }
+
+    /// Pairs the computed fields of the color at `index` with their column names.
+    fn cells(&self, index: usize) -> impl Iterator<Item = (&'static str, &Field)> {
+        self.names.iter().copied().zip(self.rows[index].iter())
+    }
+}
+
+/// Tabular output for commands whose rows aren't plain colors.
+#[derive(Debug)]
+struct Report {
+    /// The XML element name of each row.
+    element: &'static str,
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<Field>>,
+}
+
+impl Report {
+    fn new(element: &'static str, columns: &[&'static str]) -> Self {
+        Report {
+            element,
+            columns: columns.to_vec(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Pairs the non-empty fields of a row with their column names.
+    fn cells<'a>(&self, row: &'a [Field]) -> Vec<(&'static str, &'a Field)> {
+        self.columns
+            .iter()
+            .zip(row.iter())
+            .filter(|(_, field)| !matches!(field, Field::Empty))
+            .map(|(column, field)| (*column, field))
+            .collect()
+    }
+}
+
+fn generate_report(
+    report: &Report,
+    format: OutputFormat,
+    encoding: OutputEncoding,
+) -> anyhow::Result<String> {
+    let mut buf = String::new();
+
+    match format {
+        OutputFormat::Json => {
+            writeln!(buf, "[")?;
+
+            for (index, row) in report.rows.iter().enumerate() {
+                let fields = report
+                    .cells(row)
+                    .into_iter()
+                    .map(|(column, field)| format!(r#""{}":{}"#, column, field.json()))
+                    .collect::<Vec<_>>();
+
+                write!(buf, "  {{{}}}", fields.join(","))?;
+
+                if index < report.rows.len() - 1 {
+                    writeln!(buf, ",")?;
+                }
+            }
+
+            writeln!(buf)?;
+            writeln!(buf, "]")?;
+        }
+        OutputFormat::Xml => {
+            writeln!(buf, "{}", xml_declaration(encoding))?;
+            writeln!(buf, "<{}s>", report.element)?;
+
+            for row in report.rows.iter() {
+                write!(buf, "  <{}", report.element)?;
+
+                for (column, field) in report.cells(row) {
+                    write!(buf, r#" {}="{}""#, column, field.xml())?;
+                }
+
+                writeln!(buf, " />")?;
+            }
+
+            writeln!(buf, "</{}s>", report.element)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(buf, "{}", report.columns.join(","))?;
+
+            for row in report.rows.iter() {
+                let fields = row.iter().map(Field::csv).collect::<Vec<_>>();
+
+                writeln!(buf, "{}", fields.join(","))?;
+            }
+        }
+        _ => anyhow::bail!(
+            "the {} format is not supported for this command; use json, xml or csv",
+            format.name()
+        ),
+    }
+
+    Ok(buf)
+}
+
+fn generate_matrix(
+    colors: &[&Color],
+    format: OutputFormat,
+    encoding: OutputEncoding,
+) -> anyhow::Result<String> {
+    // The output grows quadratically, so keep it to something a person can read.
+    const MAX_COLORS: usize = 64;
+
+    if colors.len() > MAX_COLORS {
+        anyhow::bail!(
+            "a contrast matrix of {} colors is too large (at most {}); narrow it down with --filter or by name",
+            colors.len(),
+            MAX_COLORS
+        );
+    }
+
+    let ratios = colors
+        .iter()
+        .map(|a| {
+            colors
+                .iter()
+                .map(|b| format!("{:.2}", a.contrast_ratio(b)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut buf = String::new();
+
+    match format {
+        OutputFormat::Json => {
+            let names = colors
+                .iter()
+                .map(|color| format!(r#""{}""#, color.name))
+                .collect::<Vec<_>>();
+
+            writeln!(buf, "{{")?;
+            writeln!(buf, r#"  "names": [{}],"#, names.join(","))?;
+            writeln!(buf, r#"  "contrast": ["#)?;
+
+            for (index, row) in ratios.iter().enumerate() {
+                write!(buf, "    [{}]", row.join(","))?;
+
+                if index < ratios.len() - 1 {
+                    writeln!(buf, ",")?;
+                }
+            }
+
+            writeln!(buf)?;
+            writeln!(buf, "  ]")?;
+            writeln!(buf, "}}")?;
+        }
+        OutputFormat::Xml => {
+            writeln!(buf, "{}", xml_declaration(encoding))?;
+            writeln!(buf, "<matrix>")?;
+
+            for (a, row) in colors.iter().zip(ratios.iter()) {
+                writeln!(buf, r#"  <row name="{}">"#, a.name)?;
+
+                for (b, ratio) in colors.iter().zip(row.iter()) {
+                    writeln!(
+                        buf,
+                        r#"    <cell name="{}" contrast="{}" />"#,
+                        b.name, ratio
+                    )?;
+                }
+
+                writeln!(buf, "  </row>")?;
+            }
+
+            writeln!(buf, "</matrix>")?;
+        }
+        OutputFormat::Csv => {
+            let names = colors
+                .iter()
+                .map(|color| color.name.as_str())
+                .collect::<Vec<_>>();
+
+            writeln!(buf, ",{}", names.join(","))?;
+
+            for (name, row) in names.iter().zip(ratios.iter()) {
+                writeln!(buf, "{},{}", name, row.join(","))?;
+            }
+        }
+        _ => anyhow::bail!(
+            "the {} format is not supported for this command; use json, xml or csv",
+            format.name()
+        ),
+    }
+
+    Ok(buf)
+}
+
+fn xml_declaration(encoding: OutputEncoding) -> String {
+    format!(r#"<?xml version="1.0" encoding="{}"?>"#, encoding.charset())
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Quotes `text` when it holds a delimiter, a quote or a line break, or
+/// starts or ends with whitespace that a reader would trim.
+fn escape_csv(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) || text.trim() != text {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn generate_preview(
+    nodes: &[Color],
+    swatch_width: usize,
+    ansi: AnsiMode,
+) -> anyhow::Result<String> {
+    let hex_width = nodes
+        .iter()
+        .map(|color| color.hex().len())
+        .max()
+        .unwrap_or_default();
+    let mut buf = String::new();
+
+    for color in nodes {
+        let swatch = match ansi {
+            AnsiMode::Truecolor => format!(
+                "\x1b[48;2;{};{};{}m{:swatch_width$}\x1b[0m  ",
+                color.red, color.green, color.blue, ""
+            ),
+            AnsiMode::Ansi256 => format!(
+                "\x1b[48;5;{}m{:swatch_width$}\x1b[0m  ",
+                color.to_ansi256(),
+                ""
+            ),
+            AnsiMode::Off => String::new(),
+        };
+
+        writeln!(buf, "{}{:<hex_width$}  {}", swatch, color.hex(), color.name)?;
+    }
+
+    Ok(buf)
+}
+
+fn generate_json(
+    nodes: &[Color],
+    scale: ChannelScale,
+    precision: usize,
+    group_by: Option<GroupBy>,
+    extras: &ExtraColumns,
+) -> anyhow::Result<String> {
+    let channel = move |value: u8| scale.format(value, precision);
+    let object = move |index: usize, color: &Color| {
+        let extras = extras
+            .cells(index)
+            .map(|(name, field)| format!(r#","{}":{}"#, name, field.json()))
+            .collect::<String>();
+
+        format!(
+            r#"{{"name":{},"red":{},"green":{},"blue":{}{}}}"#,
+            serde_json::to_string(&color.name).unwrap(),
+            channel(color.red),
+            channel(color.green),
+            channel(color.blue),
+            extras
+        )
+    };
+    let mut buf = String::new();
+
+    let Some(GroupBy::Family) = group_by else {
+        writeln!(buf, "[")?;
+
+        for (index, color) in nodes.iter().enumerate() {
+            write!(buf, "  {}", object(index, color))?;
+
+            if index < nodes.len() - 1 {
+                writeln!(buf, ",")?;
+            }
+        }
+
+        writeln!(buf)?;
+        writeln!(buf, "]")?;
+
+        return Ok(buf);
+    };
+
+    let groups = group_by_family(nodes);
+
+    writeln!(buf, "{{")?;
+
+    for (group, (family, colors)) in groups.iter().enumerate() {
+        writeln!(buf, r#"  "{}": ["#, family)?;
+
+        for (position, &(index, color)) in colors.iter().enumerate() {
+            write!(buf, "    {}", object(index, color))?;
+
+            if position < colors.len() - 1 {
+                writeln!(buf, ",")?;
+            }
+        }
+
+        writeln!(buf)?;
+        write!(buf, "  ]")?;
+
+        if group < groups.len() - 1 {
+            writeln!(buf, ",")?;
+        }
+    }
+
+    writeln!(buf)?;
+    writeln!(buf, "}}")?;
+
+    Ok(buf)
+}
+
+/// Keeps the first color of each run of adjacent colors with the same RGB,
+/// returning how long each kept color's run was.
+fn collapse_runs(nodes: &mut Vec<Color>) -> Vec<usize> {
+    let mut counts: Vec<usize> = Vec::new();
+    let mut kept: Vec<Color> = Vec::new();
+
+    for color in nodes.drain(..) {
+        match kept.last() {
+            Some(last) if last.to_u32() == color.to_u32() => {
+                *counts.last_mut().expect("a count for every kept color") += 1;
+            }
+            _ => {
+                kept.push(color);
+                counts.push(1);
+            }
+        }
+    }
+
+    *nodes = kept;
+
+    counts
+}
+
+/// Buckets the colors by hue family in wheel order, omitting empty families.
+/// Each member keeps its index in `nodes`.
+fn group_by_family(nodes: &[Color]) -> Vec<(&'static str, Vec<(usize, &Color)>)> {
+    HUE_FAMILIES
+        .iter()
+        .map(|&family| {
+            let members = nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, color)| color.hue_family() == family)
+                .collect::<Vec<_>>();
+
+            (family, members)
+        })
+        .filter(|(_, members)| !members.is_empty())
+        .collect()
+}
+
+fn generate_csv(
+    nodes: &[Color],
+    scale: ChannelScale,
+    precision: usize,
+    align: bool,
+    extras: &ExtraColumns,
+) -> anyhow::Result<String> {
+    let mut header = vec![
+        "name".to_string(),
+        "red".to_string(),
+        "green".to_string(),
+        "blue".to_string(),
+    ];
+
+    header.extend(extras.names.iter().map(|name| name.to_string()));
+
+    let mut rows = vec![header];
+
+    rows.extend(nodes.iter().enumerate().map(|(index, color)| {
+        let mut row = vec![
+            escape_csv(&color.name),
+            scale.format(color.red, precision),
+            scale.format(color.green, precision),
+            scale.format(color.blue, precision),
+        ];
+
+        row.extend(extras.cells(index).map(|(_, field)| field.csv()));
+        row
+    }));
+
+    render_csv(rows, align)
+}
+
+/// Joins already-escaped rows into CSV, or into space-padded columns with
+/// `align`.
+fn render_csv(rows: Vec<Vec<String>>, align: bool) -> anyhow::Result<String> {
+    let mut buf = String::new();
+
+    if !align {
+        for row in rows {
+            writeln!(buf, "{}", row.join(","))?;
+        }
+
+        return Ok(buf);
+    }
+
+    let mut widths = vec![0; rows[0].len()];
+
+    for row in rows.iter() {
+        for (width, field) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    for row in rows {
+        // The name is left-aligned and everything after it right-aligned.
+        let fields = row
+            .iter()
+            .zip(widths.iter())
+            .enumerate()
+            .map(|(index, (field, &width))| match index {
+                0 => format!("{:<width$}", field),
+                _ => format!("{:>width$}", field),
+            })
+            .collect::<Vec<_>>();
+
+        writeln!(buf, "{}", fields.join("  "))?;
+    }
+
+    Ok(buf)
+}
+
+fn generate_xml(
+    nodes: &[Color],
+    group_by: Option<GroupBy>,
+    extras: &ExtraColumns,
+    encoding: OutputEncoding,
+) -> anyhow::Result<String> {
+    let element = |index: usize, color: &Color| {
+        let extras = extras
+            .cells(index)
+            .map(|(name, field)| format!(r#" {}="{}""#, name, field.xml()))
+            .collect::<String>();
+
+        format!(
+            r#"<color name="{}" red="{}" green="{}" blue="{}"{} />"#,
+            escape_xml(&color.name),
+            color.red,
+            color.green,
+            color.blue,
+            extras
+        )
+    };
+    let mut buf = String::new();
+
+    writeln!(buf, "{}", xml_declaration(encoding))?;
+    writeln!(buf, "<colors>")?;
+
+    match group_by {
+        None => {
+            for (index, color) in nodes.iter().enumerate() {
+                writeln!(buf, "  {}", element(index, color))?;
+            }
+        }
+        Some(GroupBy::Family) => {
+            for (family, colors) in group_by_family(nodes) {
+                writeln!(buf, r#"  <family name="{}">"#, family)?;
+
+                for (index, color) in colors {
+                    writeln!(buf, "    {}", element(index, color))?;
+                }
+
+                writeln!(buf, "  </family>")?;
+            }
+        }
+    }
+
+    writeln!(buf, "</colors>")?;
+
+    Ok(buf)
+}
+
+fn generate_template(
+    nodes: &[Color],
+    template: &Template,
+    header: &Option<String>,
+    footer: &Option<String>,
+) -> anyhow::Result<String> {
+    let mut buf = String::new();
+
+    if let Some(header) = header {
+        writeln!(buf, "{}", header)?;
+    }
+
+    for color in nodes {
+        writeln!(buf, "{}", template.render(color))?;
+    }
+
+    if let Some(footer) = footer {
+        writeln!(buf, "{}", footer)?;
+    }
+
+    Ok(buf)
+}
+
+/// Compiles a template file up front so syntax errors surface before any
+/// download. Strict mode turns a misspelled field into an error rather than
+/// silently rendering nothing.
+fn load_handlebars(path: &Path) -> anyhow::Result<Handlebars<'static>> {
+    let mut handlebars = Handlebars::new();
+
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+        .register_template_file("colors", path)
+        .with_context(|| format!("failed to load the template {}", path.display()))?;
+
+    Ok(handlebars)
+}
+
+fn generate_handlebars(nodes: &[Color], handlebars: &Handlebars) -> anyhow::Result<String> {
+    let colors = nodes
+        .iter()
+        .map(|color| {
+            let (hue, saturation, lightness) = color.to_hsl();
+
+            serde_json::json!({
+                "name": color.name,
+                "red": color.red,
+                "green": color.green,
+                "blue": color.blue,
+                "hex": color.hex(),
+                "hsl": {
+                    "hue": hue,
+                    "saturation": saturation,
+                    "lightness": lightness,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let context = serde_json::json!({ "count": colors.len(), "colors": colors });
+
+    Ok(handlebars.render("colors", &context)?)
+}
+
+/// Renders the chosen columns as a bordered table, painting the swatch column
+/// with `swatches` or leaving it out when there are none to paint.
+fn generate_table(
+    nodes: &[Color],
+    columns: &[TableColumn],
+    width: Option<u16>,
+    style: TableStyle,
+    swatches: Option<AnsiMode>,
+) -> anyhow::Result<String> {
+    let columns = columns
+        .iter()
+        .copied()
+        .filter(|&column| column != TableColumn::Swatch || swatches.is_some())
+        .collect::<Vec<_>>();
+    let mut table = Table::new();
+
+    table.load_style(match style {
+        TableStyle::Utf8 => presets::UTF8_FULL_CONDENSED,
+        TableStyle::Ascii => presets::ASCII_FULL_CONDENSED,
+    });
+
+    if swatches.is_some() {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
+
+    if let Some(width) = width {
+        table
+            .set_width(width)
+            .set_content_arrangement(ContentArrangement::Dynamic);
+    }
+
+    table.set_header(columns.iter().map(|column| match column {
+        TableColumn::Swatch => "",
+        TableColumn::Name => "name",
+        TableColumn::Hex => "hex",
+        TableColumn::Rgb => "rgb",
+    }));
+
+    for color in nodes {
+        table.add_row(columns.iter().map(|column| match column {
+            TableColumn::Swatch => Cell::new("    ").bg(match swatches {
+                Some(AnsiMode::Ansi256) => TableColor::AnsiValue(color.to_ansi256()),
+                _ => TableColor::Rgb {
+                    r: color.red,
+                    g: color.green,
+                    b: color.blue,
+                },
+            }),
+            TableColumn::Name => Cell::new(&color.name),
+            TableColumn::Hex => Cell::new(color.hex()),
+            TableColumn::Rgb => {
+                Cell::new(format!("{}, {}, {}", color.red, color.green, color.blue))
+            }
+        }));
+    }
+
+    Ok(table.to_string())
+}
+
+/// Writes `width` codes to a line, joined by `separator`. Lines after the
+/// first continue the list, so they end with the separator minus any trailing
+/// space, e.g. `#000000, #ffffff,` for `", "`.
+fn generate_hex_list(nodes: &[Color], width: usize, separator: &str) -> anyhow::Result<String> {
+    let lines = nodes
+        .chunks(width)
+        .map(|line| {
+            line.iter()
+                .map(Color::hex)
+                .collect::<Vec<_>>()
+                .join(separator)
+        })
+        .collect::<Vec<_>>();
+    let mut buf = lines.join(&format!("{}\n", separator.trim_end()));
+
+    if !buf.is_empty() {
+        buf.push('\n');
+    }
+
+    Ok(buf)
+}
+
+fn generate_jasc_pal(nodes: &[Color]) -> anyhow::Result<String> {
+    // Paint Shop Pro and friends expect DOS line endings in palette files.
+    let mut buf = String::new();
+
+    write!(buf, "JASC-PAL\r\n0100\r\n{}\r\n", nodes.len())?;
+
+    for color in nodes {
+        write!(buf, "{} {} {}\r\n", color.red, color.green, color.blue)?;
+    }
+
+    Ok(buf)
+}
+
+fn generate_gpl(nodes: &[Color], name: &str, columns: usize) -> anyhow::Result<String> {
+    let mut buf = String::new();
+
+    writeln!(buf, "GIMP Palette")?;
+    writeln!(buf, "Name: {}", name)?;
+    writeln!(buf, "Columns: {}", columns)?;
+    writeln!(buf, "#")?;
+
+    for color in nodes {
+        writeln!(
+            buf,
+            "{:>3} {:>3} {:>3}\t{}",
+            color.red, color.green, color.blue, color.name
+        )?;
+    }
+
+    Ok(buf)
+}
+
+/// Turns each color's name, between `prefix` and `suffix`, into a kebab-case
+/// identifier that starts with a letter, suffixing repeats with `-2`, `-3` and
+/// so on so every one is unique, and distinct from the `reserved` ones.
+fn unique_identifiers(
+    nodes: &[Color],
+    prefix: &str,
+    suffix: &str,
+    reserved: &[String],
+) -> Vec<String> {
+    let mut used = reserved.iter().cloned().collect::<HashSet<_>>();
+
+    nodes
+        .iter()
+        .map(|color| {
+            let mut name = format!("{}{}{}", prefix, color.name, suffix)
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+                .to_ascii_lowercase();
+
+            // A leading digit would make LESS read the variable as a number,
+            // and isn't a valid CSS identifier either.
+            if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                name.insert_str(0, "color-");
+            }
+
+            let mut unique = name.clone();
+            let mut suffix = 1;
+
+            while !used.insert(unique.clone()) {
+                suffix += 1;
+                unique = format!("{}-{}", name, suffix);
+            }
+
+            unique
+        })
+        .collect()
+}
+
+fn generate_less(nodes: &[Color], names: &[String]) -> anyhow::Result<String> {
+    let mut buf = String::new();
+
+    for (name, color) in names.iter().zip(nodes) {
+        writeln!(buf, "@{}: {};", name, color.hex())?;
+    }
+
+    Ok(buf)
+}
+
+fn generate_sql(nodes: &[Color], dialect: SqlDialect) -> anyhow::Result<String> {
+    let table = dialect.identifier("colors");
+    let columns = ["name", "red", "green", "blue"].map(|column| dialect.identifier(column));
+    let (text, channel) = dialect.types();
+    let mut buf = String::new();
+
+    let definitions = columns
+        .iter()
+        .zip([text, channel, channel, channel])
+        .map(|(column, kind)| format!("{} {} NOT NULL", column, kind))
+        .collect::<Vec<_>>();
+
+    writeln!(buf, "CREATE TABLE {} ({});", table, definitions.join(", "))?;
+
+    for color in nodes {
+        writeln!(
+            buf,
+            "INSERT INTO {} ({}) VALUES ({}, {}, {}, {});",
+            table,
+            columns.join(", "),
+            dialect.string(&color.name),
+            color.red,
+            color.green,
+            color.blue
+        )?;
+    }
+
+    Ok(buf)
+}
+
+fn generate_css(
+    nodes: &[Color],
+    names: &[String],
+    themes: bool,
+    syntax: CssColorSyntax,
+    rounding: Rounding,
+) -> anyhow::Result<String> {
+    let mut buf = String::new();
+
+    writeln!(buf, ":root {{")?;
+
+    for (name, color) in names.iter().zip(nodes) {
+        writeln!(buf, "  --{}: {};", name, syntax.format(color))?;
+    }
+
+    writeln!(buf, "}}")?;
+
+    // The dark block reuses the same names so stylesheets switch themes
+    // without changing which variables they reference.
+    if themes {
+        writeln!(buf)?;
+        writeln!(buf, "@media (prefers-color-scheme: dark) {{")?;
+        writeln!(buf, "  :root {{")?;
+
+        for (name, color) in names.iter().zip(nodes) {
+            writeln!(
+                buf,
+                "    --{}: {};",
+                name,
+                syntax.format(&color.invert_lightness(rounding))
+            )?;
+        }
+
+        writeln!(buf, "  }}")?;
+        writeln!(buf, "}}")?;
+    }
+
+    Ok(buf)
+}
+
+fn generate_html(
+    nodes: &[Color],
+    with_index: bool,
+    group_by: Option<GroupBy>,
+    extras: &ExtraColumns,
+    encoding: OutputEncoding,
+) -> anyhow::Result<String> {
+    // The family headings share the page's ids with the rows.
+    let headings = match group_by {
+        None => Vec::new(),
+        Some(GroupBy::Family) => HUE_FAMILIES
+            .iter()
+            .map(|family| family_id(family))
+            .collect(),
+    };
+    let ids = unique_identifiers(nodes, "", "", &headings);
+    let mut buf = String::new();
+
+    writeln!(buf, "<!DOCTYPE html>")?;
+    writeln!(buf, r#"<html lang="en">"#)?;
+    writeln!(buf, "<head>")?;
+    writeln!(buf, r#"<meta charset="{}">"#, encoding.charset())?;
+    writeln!(buf, "<title>Colors</title>")?;
+    writeln!(
+        buf,
+        "<style>table {{ border-collapse: collapse; }} td, th {{ padding: 0.25em 0.75em; text-align: left; }} td.swatch {{ width: 3em; }}</style>"
+    )?;
+    writeln!(buf, "</head>")?;
+    writeln!(buf, "<body>")?;
+
+    if with_index {
+        writeln!(buf, "<nav>")?;
+        writeln!(buf, "<ul>")?;
+
+        // The table keeps the output order, but an index is easier to scan by name.
+        let mut entries = ids.iter().zip(nodes).collect::<Vec<_>>();
+
+        entries.sort_by_cached_key(|(_, color)| color.name.to_lowercase());
+
+        for (id, color) in entries {
+            writeln!(
+                buf,
+                r##"<li><a href="#{}">{}</a></li>"##,
+                id,
+                escape_xml(&color.name)
+            )?;
+        }
+
+        writeln!(buf, "</ul>")?;
+        writeln!(buf, "</nav>")?;
+    }
+
+    match group_by {
+        None => write_html_table(
+            &mut buf,
+            &nodes.iter().enumerate().collect::<Vec<_>>(),
+            &ids,
+            extras,
+        )?,
+        Some(GroupBy::Family) => {
+            for (family, members) in group_by_family(nodes) {
+                writeln!(buf, r#"<h2 id="{}">{}</h2>"#, family_id(family), family)?;
+                write_html_table(&mut buf, &members, &ids, extras)?;
+            }
+        }
+    }
 
-    let data = match args.format {
-        OutputFormat::Json => generate_json(nodes)?,
-        OutputFormat::Xml => generate_xml(nodes)?,
-        OutputFormat::Csv => generate_csv(nodes)?,
-    };
+    writeln!(buf, "</body>")?;
+    writeln!(buf, "</html>")?;
 
-    println!("{}", data);
+    Ok(buf)
+}
 
-    Ok(())
+fn family_id(family: &str) -> String {
+    format!("family-{}", family)
 }
 
-fn generate_json(nodes: Vec<Color>) -> anyhow::Result<String> {
-    let mut buf = String::new();
+/// Writes one `<table>` of `rows`, each paired with its index in the full
+/// list so it finds its identifier and extra columns.
+fn write_html_table(
+    buf: &mut String,
+    rows: &[(usize, &Color)],
+    ids: &[String],
+    extras: &ExtraColumns,
+) -> anyhow::Result<()> {
+    writeln!(buf, "<table>")?;
+    write!(
+        buf,
+        "<thead><tr><th></th><th>name</th><th>hex</th><th>red</th><th>green</th><th>blue</th>"
+    )?;
+
+    for name in extras.names.iter() {
+        write!(buf, "<th>{}</th>", name)?;
+    }
+
+    writeln!(buf, "</tr></thead>")?;
+    writeln!(buf, "<tbody>")?;
 
-    writeln!(buf, "[")?;
+    for &(index, color) in rows {
+        let id = &ids[index];
 
-    for (index, color) in nodes.iter().enumerate() {
         write!(
             buf,
-            r#"  {{"name":"{}","red":{},"green":{},"blue":{}}}"#,
-            color.name, color.red, color.green, color.blue
+            r#"<tr id="{}"><td class="swatch" style="background: {}"></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>"#,
+            id,
+            color.hex(),
+            escape_xml(&color.name),
+            color.hex(),
+            color.red,
+            color.green,
+            color.blue
         )?;
 
-        if index < nodes.len() - 1 {
-            writeln!(buf, ",")?;
+        for (_, field) in extras.cells(index) {
+            write!(buf, "<td>{}</td>", field.xml())?;
         }
+
+        writeln!(buf, "</tr>")?;
     }
 
-    writeln!(buf)?;
-    writeln!(buf, "]")?;
+    writeln!(buf, "</tbody>")?;
+    writeln!(buf, "</table>")?;
 
-    Ok(buf)
+    Ok(())
 }
 
-fn generate_csv(nodes: Vec<Color>) -> anyhow::Result<String> {
-    let mut buf = String::new();
+/// Writes an Adobe Swatch Exchange file with one RGB color entry per color.
+fn generate_ase(nodes: &[Color]) -> anyhow::Result<Vec<u8>> {
+    const COLOR_ENTRY: u16 = 0x0001;
+    const NORMAL_COLOR: u16 = 2;
 
-    writeln!(buf, "name,red,green,blue")?;
+    let mut buf = Vec::new();
+
+    buf.extend_from_slice(b"ASEF");
+    buf.extend_from_slice(&1u16.to_be_bytes());
+    buf.extend_from_slice(&0u16.to_be_bytes());
+    buf.extend_from_slice(&u32::try_from(nodes.len())?.to_be_bytes());
 
     for color in nodes {
-        writeln!(
-            buf,
-            "{},{},{},{}",
-            color.name, color.red, color.green, color.blue
-        )?;
+        // Names are null-terminated UTF-16 and their length counts the terminator.
+        let name = color.name.encode_utf16().chain([0]).collect::<Vec<_>>();
+        let length = 2 + name.len() * 2 + 4 + 3 * 4 + 2;
+
+        buf.extend_from_slice(&COLOR_ENTRY.to_be_bytes());
+        buf.extend_from_slice(&u32::try_from(length)?.to_be_bytes());
+        buf.extend_from_slice(&u16::try_from(name.len())?.to_be_bytes());
+
+        for unit in name {
+            buf.extend_from_slice(&unit.to_be_bytes());
+        }
+
+        buf.extend_from_slice(b"RGB ");
+
+        for channel in [color.red, color.green, color.blue] {
+            buf.extend_from_slice(&(channel as f32 / 255.0).to_be_bytes());
+        }
+
+        buf.extend_from_slice(&NORMAL_COLOR.to_be_bytes());
     }
 
     Ok(buf)
 }
 
-fn generate_xml(nodes: Vec<Color>) -> anyhow::Result<String> {
-    let mut buf = String::new();
+/// Renders the colors as square swatches, left to right and then top to
+/// bottom in `rows` rows, optionally with each name below its swatch.
+fn generate_image(
+    nodes: &[Color],
+    size: u32,
+    rows: u32,
+    label: bool,
+    visual_order: bool,
+) -> anyhow::Result<Vec<u8>> {
+    // An 8x8 glyph with a pixel of padding above and below.
+    const LABEL_HEIGHT: u32 = 10;
 
-    writeln!(buf, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
-    writeln!(buf, "<colors>")?;
+    if nodes.is_empty() {
+        anyhow::bail!("there are no colors to draw");
+    }
 
-    for color in nodes {
-        writeln!(
-            buf,
-            r#"  <color name="{}" red="{}" green="{}" blue="{}" />"#,
-            color.name, color.red, color.green, color.blue
-        )?;
+    let order = if visual_order {
+        chain_by_similarity(nodes, None)
+    } else {
+        (0..nodes.len()).collect()
+    };
+    let count = u32::try_from(nodes.len())?;
+    let columns = count.div_ceil(rows.min(count));
+    // Filling whole rows can take fewer than asked, e.g. 4 colors in 3 rows
+    // need 2 columns, which fill only 2 rows.
+    let rows = count.div_ceil(columns);
+    let cell_height = size + if label { LABEL_HEIGHT } else { 0 };
+    let too_big = || anyhow::anyhow!("the image would be too large; use a smaller --swatch-size");
+    let width = columns.checked_mul(size).ok_or_else(too_big)?;
+    let height = rows.checked_mul(cell_height).ok_or_else(too_big)?;
+    let mut image = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+
+    for (position, &index) in order.iter().enumerate() {
+        let color = &nodes[index];
+        let (x, y) = (
+            position as u32 % columns * size,
+            position as u32 / columns * cell_height,
+        );
+
+        for dy in 0..size {
+            for dx in 0..size {
+                image.put_pixel(x + dx, y + dy, Rgb([color.red, color.green, color.blue]));
+            }
+        }
+
+        if !label {
+            continue;
+        }
+
+        // Keep a pixel of margin on each side so neighbouring labels don't run
+        // together; glyphs without a bitmap (most non-ASCII) are left blank.
+        let fits = (size.saturating_sub(2) / 8) as usize;
+
+        for (slot, c) in color.name.chars().take(fits).enumerate() {
+            let Some(glyph) = BASIC_FONTS.get(c) else {
+                continue;
+            };
+
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..8 {
+                    if bits >> column & 1 == 1 {
+                        image.put_pixel(
+                            x + 1 + slot as u32 * 8 + column,
+                            y + size + 1 + row as u32,
+                            Rgb([0, 0, 0]),
+                        );
+                    }
+                }
+            }
+        }
     }
 
-    writeln!(buf, "</colors>")?;
+    let mut buf = Vec::new();
+
+    image.write_to(&mut io::Cursor::new(&mut buf), ImageFormat::Png)?;
 
     Ok(buf)
 }
 
-async fn load_colors() -> anyhow::Result<Vec<Color>> {
-    const URL: &str = "https://en.wikipedia.org/wiki/List_of_colors_(alphabetical)";
+/// Orders the colors as a greedy nearest-neighbour walk through CIELAB space,
+/// starting from the one closest to `start` (or the darkest), so that each
+/// color is followed by the closest one not yet placed. Returns indices into
+/// `nodes`.
+fn chain_by_similarity(nodes: &[Color], start: Option<&Color>) -> Vec<usize> {
+    let labs = nodes.iter().map(Color::to_lab).collect::<Vec<_>>();
+    let distance = |a: usize, b: usize| {
+        let (l1, a1, b1) = labs[a];
+        let (l2, a2, b2) = labs[b];
+
+        ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+    };
+    let mut remaining = (0..nodes.len()).collect::<Vec<_>>();
+    let mut order = Vec::with_capacity(nodes.len());
+    let mut next = match start {
+        Some(start) => remaining
+            .iter()
+            .copied()
+            .min_by(|&a, &b| nodes[a].delta_e(start).total_cmp(&nodes[b].delta_e(start))),
+        None => remaining
+            .iter()
+            .copied()
+            .min_by(|&a, &b| labs[a].0.total_cmp(&labs[b].0)),
+    };
 
-    let client = Client::new();
-    let html = client.get(URL).send().await?.text().await?;
-    let document = Html::parse_document(&html);
-    let selector = Selector::parse("div.mw-content-ltr > div > p").unwrap();
-    let color_data_regex =
-        Regex::new(r"𝗥𝗚𝗕\s+\((?<red>\d+)\s+(?<green>\d+)\s+(?<blue>\d+)\)").unwrap();
-    let color_extractor = move |e: ElementRef| {
-        let text = e.text().collect::<String>();
-        let text = text.trim();
+    while let Some(current) = next {
+        remaining.retain(|&index| index != current);
+        order.push(current);
+        next = remaining
+            .iter()
+            .copied()
+            .min_by(|&a, &b| distance(current, a).total_cmp(&distance(current, b)));
+    }
 
-        if text.is_empty() {
-            let values = e.attr("title").unwrap();
-            let caps = color_data_regex.captures(values).unwrap();
-            let red = caps["red"].parse::<u8>().unwrap();
-            let green = caps["green"].parse::<u8>().unwrap();
-            let blue = caps["blue"].parse::<u8>().unwrap();
+    order
+}
 
-            Component::Rgb(red, green, blue)
-        } else {
-            let text = text
-                .replace(move |c: char| c == '(' || c == ')', "")
-                .replace(move |c: char| !c.is_ascii_alphanumeric(), "_")
-                .to_lowercase()
-                .to_string();
+/// Reads a local file as text, dropping a UTF-8 byte order mark and replacing
+/// any bytes that aren't valid UTF-8 rather than failing.
+fn read_text(path: &Path, verbose: bool) -> anyhow::Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let bytes = match bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        Some(rest) => {
+            if verbose {
+                eprintln!("{}: skipped a UTF-8 byte order mark", path.display());
+            }
 
-            Component::Name(text)
+            rest
         }
+        None => &bytes,
     };
 
-    let nodes = document
-        .select(&selector)
-        .map(color_extractor)
-        .collect::<Vec<_>>();
-    let nodes = nodes
-        .chunks(2)
-        .map(move |pair| {
-            let mut color = Color {
-                name: String::new(),
-                red: 0,
-                green: 0,
-                blue: 0,
+    match String::from_utf8_lossy(bytes) {
+        Cow::Borrowed(text) => Ok(text.to_string()),
+        Cow::Owned(text) => {
+            if verbose {
+                eprintln!(
+                    "{}: not valid UTF-8; invalid bytes were replaced",
+                    path.display()
+                );
+            }
+
+            Ok(text)
+        }
+    }
+}
+
+/// A color as read back from an export, before its channels are checked.
+#[derive(Deserialize)]
+struct ImportedColor {
+    name: String,
+    red: f64,
+    green: f64,
+    blue: f64,
+}
+
+impl ImportedColor {
+    /// Checks that every channel is a whole number from 0 to 255. Otherwise it
+    /// fails under `strict`, or warns and rounds and clamps the channel.
+    fn validate(self, strict: bool) -> anyhow::Result<Color> {
+        let channel = |key: &str, value: f64| {
+            if (0.0..=255.0).contains(&value) && value.fract() == 0.0 {
+                return Ok(value as u8);
+            }
+
+            if strict {
+                anyhow::bail!(
+                    "`{}` has a {} of {}, not a whole number from 0 to 255",
+                    self.name,
+                    key,
+                    value
+                );
+            }
+
+            let clamped = to_u8(value, Rounding::Nearest);
+
+            eprintln!(
+                "warning: `{}` has a {} of {}; using {}",
+                self.name, key, value, clamped
+            );
+
+            Ok(clamped)
+        };
+
+        Ok(Color {
+            red: channel("red", self.red)?,
+            green: channel("green", self.green)?,
+            blue: channel("blue", self.blue)?,
+            name: self.name,
+        })
+    }
+}
+
+fn import_colors(path: &Path, verbose: bool, strict: bool) -> anyhow::Result<Vec<Color>> {
+    let data = read_text(path, verbose)?;
+    let is_json = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("json") => true,
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => false,
+        _ => data.trim_start().starts_with('['),
+    };
+
+    let imported = if is_json {
+        serde_json::from_str::<Vec<ImportedColor>>(&data)
+            .with_context(|| format!("invalid JSON in {}", path.display()))?
+    } else {
+        parse_csv(&data).with_context(|| format!("invalid CSV in {}", path.display()))?
+    };
+
+    imported
+        .into_iter()
+        .map(|color| color.validate(strict))
+        .collect::<anyhow::Result<_>>()
+        .with_context(|| format!("invalid channel in {}", path.display()))
+}
+
+fn parse_csv(data: &str) -> anyhow::Result<Vec<ImportedColor>> {
+    let mut records = csv_records(data)?.into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("missing header row"))?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| anyhow::anyhow!("missing `{}` column", name))
+    };
+    let (name, red, green, blue) = (
+        column("name")?,
+        column("red")?,
+        column("green")?,
+        column("blue")?,
+    );
+
+    records
+        .enumerate()
+        .map(|(index, fields)| {
+            let field = |column: usize| {
+                fields
+                    .get(column)
+                    .map(String::as_str)
+                    .ok_or_else(|| anyhow::anyhow!("row {} is missing fields", index + 1))
             };
 
-            for component in pair.iter() {
-                match component {
-                    Component::Name(name) => color.name.clone_from(name),
-                    Component::Rgb(red, green, blue) => {
-                        color.red = *red;
-                        color.green = *green;
-                        color.blue = *blue;
-                    }
+            Ok(ImportedColor {
+                name: field(name)?.to_string(),
+                red: field(red)?.parse()?,
+                green: field(green)?.parse()?,
+                blue: field(blue)?.parse()?,
+            })
+        })
+        .collect()
+}
+
+/// Splits CSV into records of fields, reading quoted fields the way
+/// [`escape_csv`] writes them: commas and line breaks inside quotes are kept
+/// and `""` is a literal quote. Unquoted fields are trimmed, and blank lines
+/// are skipped.
+fn csv_records(data: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let (mut quoted, mut in_quotes) = (false, false);
+    let mut chars = data.chars().peekable();
+    let finish = |field: &mut String, quoted: &mut bool| {
+        let text = std::mem::take(field);
+
+        if std::mem::take(quoted) {
+            text
+        } else {
+            text.trim().to_string()
+        }
+    };
+
+    while let Some(character) = chars.next() {
+        match character {
+            '"' if in_quotes => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    in_quotes = false;
                 }
             }
+            '"' if !quoted && field.trim().is_empty() => {
+                field.clear();
+                (quoted, in_quotes) = (true, true);
+            }
+            ',' if !in_quotes => record.push(finish(&mut field, &mut quoted)),
+            '\n' if !in_quotes => {
+                record.push(finish(&mut field, &mut quoted));
+                records.push(std::mem::take(&mut record));
+            }
+            // Whatever follows the closing quote, up to the comma, is padding.
+            _ if quoted && !in_quotes => {}
+            '\r' if !in_quotes => {}
+            _ => field.push(character),
+        }
+    }
 
-            color
-        })
-        .collect();
+    if in_quotes {
+        anyhow::bail!("a quoted field is never closed");
+    }
+
+    record.push(finish(&mut field, &mut quoted));
+    records.push(record);
+    records.retain(|record| record.len() > 1 || !record[0].is_empty());
+
+    Ok(records)
+}
+
+/// Preferred display names, keyed by slug or by color.
+struct NameMap {
+    by_slug: HashMap<String, String>,
+    by_channels: HashMap<(u8, u8, u8), String>,
+}
+
+impl NameMap {
+    fn load(path: &Path, verbose: bool) -> anyhow::Result<Self> {
+        let data = read_text(path, verbose)?;
+        let entries: Vec<(String, String)> = if data.trim_start().starts_with('{') {
+            serde_json::from_str::<HashMap<String, String>>(&data)
+                .with_context(|| format!("invalid JSON in {}", path.display()))?
+                .into_iter()
+                .collect()
+        } else {
+            data.lines()
+                .filter(|line| !line.trim().is_empty())
+                .skip(1)
+                .map(|line| {
+                    // The last comma, so rgb(r, g, b) keys work unquoted.
+                    line.rsplit_once(',')
+                        .map(|(key, name)| (key.trim().to_string(), name.trim().to_string()))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("`{}` in {} has no name column", line, path.display())
+                        })
+                })
+                .collect::<anyhow::Result<_>>()?
+        };
+        let mut names = NameMap {
+            by_slug: HashMap::new(),
+            by_channels: HashMap::new(),
+        };
+
+        for (key, name) in entries {
+            // A bare hex code could also be a slug (e.g. `add`), so colors need
+            // their `#` or `rgb(` to be read as colors.
+            if key.starts_with('#') || key.to_ascii_lowercase().starts_with("rgb(") {
+                let (red, green, blue, _) = parse_color_input(&key)
+                    .with_context(|| format!("invalid key in {}", path.display()))?;
+
+                names.by_channels.insert((red, green, blue), name);
+            } else {
+                names.by_slug.insert(key, name);
+            }
+        }
+
+        Ok(names)
+    }
+
+    /// The name for `color`, preferring a match on its slug over its channels.
+    fn get(&self, color: &Color) -> Option<&str> {
+        self.by_slug
+            .get(&color.name)
+            .or_else(|| self.by_channels.get(&(color.red, color.green, color.blue)))
+            .map(String::as_str)
+    }
+}
+
+/// Concatenates the colors of several pages in order, keeping only the first
+/// of any color that appears on more than one with the same channels, and
+/// numbering the names that are left on more than one page.
+fn merge_pages(pages: Vec<Vec<(String, Color)>>) -> Vec<(String, Color)> {
+    let mut seen = HashSet::new();
+    let mut merged = pages
+        .into_iter()
+        .flatten()
+        .filter(|(_, color)| seen.insert((color.name.clone(), color.red, color.green, color.blue)))
+        .collect::<Vec<_>>();
+
+    number_repeats(merged.iter_mut().map(|(_, color)| color));
+
+    merged
+}
+
+/// Parses every saved page and merges the results, dropping exact duplicates.
+fn load_local_colors(
+    paths: &[PathBuf],
+    verbose: bool,
+    options: ParseOptions,
+) -> anyhow::Result<Vec<(String, Color)>> {
+    let mut pages = Vec::new();
+
+    for path in paths {
+        let html = read_text(path, verbose)?;
+        let colors = parse_html(&html, options)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        if verbose {
+            eprintln!("{}: {} colors", path.display(), colors.len());
+        }
+
+        pages.push(colors);
+    }
+
+    let nodes = merge_pages(pages);
+
+    if verbose {
+        eprintln!("{} colors after merging", nodes.len());
+    }
 
     Ok(nodes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(name: &str, red: u8, green: u8, blue: u8) -> Color {
+        Color {
+            name: name.to_string(),
+            red,
+            green,
+            blue,
+        }
+    }
+
+    /// The four colors of the bundled self-test page.
+    fn fixture() -> Vec<Color> {
+        parse_colors(SELF_TEST_PAGE)
+    }
+
+    /// The name and channels of each color, for comparing colors.
+    fn rows(nodes: &[Color]) -> Vec<(String, u8, u8, u8)> {
+        nodes
+            .iter()
+            .map(|color| (color.name.clone(), color.red, color.green, color.blue))
+            .collect()
+    }
+
+    fn names(nodes: &[Color]) -> Vec<&str> {
+        nodes.iter().map(|color| color.name.as_str()).collect()
+    }
+
+    fn sorted(mut nodes: Vec<Color>, key: SortKey) -> Vec<String> {
+        sort_colors(&mut nodes, key, 0.0, None);
+
+        nodes.into_iter().map(|color| color.name).collect()
+    }
+
+    fn no_extras(nodes: &[Color]) -> ExtraColumns {
+        ExtraColumns::compute(&[], nodes, 1).unwrap()
+    }
+
+    #[test]
+    fn clipboard_gets_the_output() {
+        let args = CommandLine::parse_from(["colors", "--clipboard"]);
+
+        write_output(&args, b"#ff0000\n".to_vec()).unwrap();
+
+        assert_eq!(
+            CLIPBOARD.with(|clipboard| clipboard.borrow_mut().take()),
+            Some("#ff0000\n".to_string())
+        );
+    }
+
+    #[test]
+    fn json_escapes_names() {
+        let nodes = [color(r#"Say "hi" \ bye"#, 1, 2, 3)];
+        let json = generate_json(&nodes, ChannelScale::Byte, 3, None, &no_extras(&nodes)).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed[0]["name"], r#"Say "hi" \ bye"#);
+    }
+
+    #[test]
+    fn xml_escapes_names() {
+        let nodes = [color("Black & <White> \"Tuxedo\"", 0, 0, 0)];
+        let xml = generate_xml(&nodes, None, &no_extras(&nodes), OutputEncoding::Utf8).unwrap();
+
+        assert!(xml.contains(
+            r#"<color name="Black &amp; &lt;White&gt; &quot;Tuxedo&quot;" red="0" green="0" blue="0" />"#
+        ));
+    }
+
+    #[test]
+    fn merged_pages_keep_distinct_names() {
+        let page = |colors: Vec<Color>| {
+            colors
+                .into_iter()
+                .map(|color| (color.name.clone(), color))
+                .collect::<Vec<_>>()
+        };
+        let merged = merge_pages(vec![
+            page(vec![color("red", 255, 0, 0), color("blue", 0, 0, 255)]),
+            page(vec![
+                color("red", 255, 0, 0),
+                color("green", 0, 128, 0),
+                color("blue", 0, 0, 250),
+            ]),
+        ]);
+        let names = merged
+            .iter()
+            .map(|(_, color)| color.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["red", "blue", "green", "blue_2"]);
+    }
+
+    #[test]
+    fn image_drops_rows_left_empty() {
+        let nodes = [
+            color("red", 255, 0, 0),
+            color("green", 0, 255, 0),
+            color("blue", 0, 0, 255),
+            color("black", 0, 0, 0),
+        ];
+        let png = generate_image(&nodes, 4, 3, false, false).unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+
+        assert_eq!((image.width(), image.height()), (8, 8));
+    }
+
+    #[test]
+    fn affixes_are_part_of_the_identifier() {
+        let args =
+            CommandLine::parse_from(["colors", "--name-prefix", "brand_", "--name-suffix", " 2"]);
+        let nodes = [color("light_blue", 173, 216, 230)];
+        let generate =
+            |format| String::from_utf8(generate(&args, &nodes, None, format).unwrap()).unwrap();
+
+        assert_eq!(
+            generate(OutputFormat::Less),
+            "@brand-light-blue-2: #add8e6;\n"
+        );
+        assert!(generate(OutputFormat::Css).contains("  --brand-light-blue-2: #add8e6;\n"));
+    }
+
+    #[test]
+    fn affixes_still_start_with_a_letter() {
+        let nodes = [color("red", 255, 0, 0)];
+
+        assert_eq!(
+            unique_identifiers(&nodes, "1 x_", "", &[]),
+            ["color-1-x-red"]
+        );
+    }
+
+    #[test]
+    fn preview_aligns_the_names() {
+        let nodes = [
+            color("red", 255, 0, 0),
+            color("dark_gray_x11", 169, 169, 169),
+        ];
+        let preview = generate_preview(&nodes, 4, AnsiMode::Off).unwrap();
+        let columns = preview
+            .lines()
+            .zip(&nodes)
+            .map(|(line, color)| line.find(&color.name).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(columns, [9, 9]);
+    }
+
+    #[test]
+    fn hex_list_writes_one_code_per_line() {
+        let nodes = fixture();
+        let list = generate_hex_list(&nodes, 1, " ").unwrap();
+        let lines = list.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), nodes.len());
+        assert!(lines.iter().all(|line| line.len() == 7
+            && line.starts_with('#')
+            && line[1..].chars().all(|c| c.is_ascii_hexdigit())));
+    }
+
+    #[test]
+    fn jasc_pal_declares_as_many_colors_as_it_lists() {
+        let nodes = fixture();
+        let pal = generate_jasc_pal(&nodes).unwrap();
+        let lines = pal.split_terminator("\r\n").collect::<Vec<_>>();
+
+        assert_eq!(lines[..2], ["JASC-PAL", "0100"]);
+        assert_eq!(lines[2].parse::<usize>().unwrap(), lines.len() - 3);
+        assert_eq!(
+            lines[3..],
+            ["173 216 230", "255 0 0", "169 169 169", "0 128 0"]
+        );
+    }
+
+    #[test]
+    fn ase_reads_back_names_and_colors() {
+        let nodes = fixture();
+        let ase = generate_ase(&nodes).unwrap();
+        let u16_at = |at: usize| u16::from_be_bytes([ase[at], ase[at + 1]]);
+        let u32_at = |at: usize| u32::from_be_bytes(ase[at..at + 4].try_into().unwrap());
+        let mut read = Vec::new();
+        let mut at = 12;
+
+        assert_eq!(&ase[..4], b"ASEF");
+        assert_eq!(u32_at(8) as usize, nodes.len());
+
+        while at < ase.len() {
+            let (kind, length) = (u16_at(at), u32_at(at + 2) as usize);
+            let units = u16_at(at + 6) as usize;
+            let name = (0..units - 1)
+                .map(|unit| u16_at(at + 8 + unit * 2))
+                .collect::<Vec<_>>();
+            let model = at + 8 + units * 2;
+            let channel = |index: usize| {
+                let value =
+                    f32::from_be_bytes(ase[model + 4 + index * 4..][..4].try_into().unwrap());
+
+                (value * 255.0).round() as u8
+            };
+
+            assert_eq!(kind, 1);
+            assert_eq!(&ase[model..model + 4], b"RGB ");
+            read.push((
+                String::from_utf16(&name).unwrap(),
+                channel(0),
+                channel(1),
+                channel(2),
+            ));
+            at += 6 + length;
+        }
+
+        assert_eq!(read, rows(&nodes));
+    }
+
+    #[test]
+    fn lightness_sorts_dark_to_light() {
+        let nodes = vec![
+            color("white", 255, 255, 255),
+            color("black", 0, 0, 0),
+            color("gray", 128, 128, 128),
+            color("dark_gray", 64, 64, 64),
+        ];
+
+        assert_eq!(
+            sorted(nodes, SortKey::Lightness),
+            ["black", "dark_gray", "gray", "white"]
+        );
+    }
+
+    #[test]
+    fn lightness_and_luminance_disagree_on_blue() {
+        // Rec. 601 luma puts pure blue below a dark green; CIELAB puts it above.
+        let nodes = || vec![color("blue", 0, 0, 255), color("dark_green", 0, 60, 0)];
+
+        assert_eq!(sorted(nodes(), SortKey::Luminance), ["blue", "dark_green"]);
+        assert_eq!(sorted(nodes(), SortKey::Lightness), ["dark_green", "blue"]);
+    }
+
+    #[test]
+    fn hue_sort_starts_at_hue_start() {
+        let nodes = || {
+            vec![
+                color("gray", 128, 128, 128),
+                color("blue", 0, 0, 255),
+                color("green", 0, 255, 0),
+                color("red", 255, 0, 0),
+            ]
+        };
+        let sorted_from = |hue_start| {
+            let mut nodes = nodes();
+
+            sort_colors(&mut nodes, SortKey::Hue, hue_start, None);
+
+            nodes
+                .into_iter()
+                .map(|color| color.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted_from(0.0), ["red", "green", "blue", "gray"]);
+        assert_eq!(sorted_from(120.0), ["green", "blue", "red", "gray"]);
+        assert_eq!(sorted_from(-120.0), ["blue", "red", "green", "gray"]);
+    }
+
+    #[test]
+    fn json_groups_keep_every_color() {
+        let nodes = fixture();
+        let json = generate_json(
+            &nodes,
+            ChannelScale::Byte,
+            3,
+            Some(GroupBy::Family),
+            &no_extras(&nodes),
+        )
+        .unwrap();
+        let groups: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&json).unwrap();
+
+        assert!(groups
+            .keys()
+            .all(|family| HUE_FAMILIES.contains(&family.as_str())));
+        assert_eq!(
+            groups
+                .values()
+                .map(|colors| colors.as_array().unwrap().len())
+                .sum::<usize>(),
+            nodes.len()
+        );
+    }
+
+    #[test]
+    fn thinning_drops_near_identical_colors() {
+        let nodes = vec![
+            color("red", 255, 0, 0),
+            color("almost_red", 254, 1, 0),
+            color("blue", 0, 0, 255),
+        ];
+        let kept = thin_colors(nodes, 10.0, DistanceMetric::Euclidean);
+
+        assert_eq!(names(&kept), ["red", "blue"]);
+    }
+
+    #[test]
+    fn collisions_list_both_page_names() {
+        let page = parse_page(
+            r#"<div class="mw-content-ltr"><div>
+            <p>Light Blue</p><p title="(173 216 230)"></p>
+            <p>Red</p><p title="(255 0 0)"></p>
+            <p>Light-Blue</p><p title="(172 216 230)"></p>
+            </div></div>"#,
+        );
+
+        assert_eq!(
+            find_collisions(&page.colors),
+            [(
+                "light_blue".to_string(),
+                vec![("Light Blue", "light_blue"), ("Light-Blue", "light_blue_2")]
+            )]
+        );
+    }
+
+    #[test]
+    fn stretch_spreads_each_channel_to_the_extremes() {
+        let mut nodes = vec![
+            color("dark", 100, 110, 120),
+            color("mid", 120, 120, 120),
+            color("light", 140, 130, 121),
+        ];
+
+        stretch_channels(&mut nodes, Rounding::Nearest);
+
+        assert_eq!(
+            rows(&nodes),
+            [
+                ("dark".to_string(), 0, 0, 0),
+                ("mid".to_string(), 128, 128, 0),
+                ("light".to_string(), 255, 255, 255),
+            ]
+        );
+    }
+
+    #[test]
+    fn saturation_and_value_sorts_put_muted_and_dark_first() {
+        let nodes = || {
+            vec![
+                color("vivid_red", 255, 0, 0),
+                color("muted_red", 160, 120, 120),
+                color("dark_red", 100, 0, 0),
+            ]
+        };
+
+        assert_eq!(
+            sorted(nodes(), SortKey::Saturation),
+            ["muted_red", "dark_red", "vivid_red"]
+        );
+        assert_eq!(
+            sorted(nodes(), SortKey::Value),
+            ["dark_red", "muted_red", "vivid_red"]
+        );
+    }
+
+    #[test]
+    fn handlebars_template_renders_the_fixture() {
+        let path = env::temp_dir().join(format!("colors-test-{}.hbs", std::process::id()));
+
+        fs::write(
+            &path,
+            "{{count}} colors:{{#each colors}} {{name}}={{hex}}{{/each}}",
+        )
+        .unwrap();
+
+        let handlebars = load_handlebars(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(
+            generate_handlebars(&fixture(), &handlebars).unwrap(),
+            "4 colors: light_blue=#add8e6 red=#ff0000 dark_gray_x11=#a9a9a9 office_green=#008000"
+        );
+    }
+
+    #[test]
+    fn parallel_columns_match_serial_ones() {
+        let nodes = (0..=255)
+            .map(|value| color(&format!("c{}", value), value, 255 - value, value / 2))
+            .collect::<Vec<_>>();
+        let extras = [Extra::TextColor, Extra::Int(IntFormat::Hex)];
+        let json = |jobs| {
+            let extras = ExtraColumns::compute(&extras, &nodes, jobs).unwrap();
+
+            generate_json(&nodes, ChannelScale::Byte, 3, None, &extras).unwrap()
+        };
+
+        assert_eq!(json(4), json(1));
+    }
+
+    #[test]
+    fn less_variables_are_sanitized() {
+        let nodes = [
+            color("Dark Gray (X11)", 169, 169, 169),
+            color("dark_gray_x11", 168, 168, 168),
+            color("100 Mph", 200, 20, 40),
+        ];
+        let less = generate_less(&nodes, &unique_identifiers(&nodes, "", "", &[])).unwrap();
+
+        assert_eq!(
+            less,
+            "@dark-gray-x11: #a9a9a9;\n@dark-gray-x11-2: #a8a8a8;\n@color-100-mph: #c81428;\n"
+        );
+    }
+
+    #[test]
+    fn similarity_chains_each_color_to_the_closest_left() {
+        let nodes = [
+            color("white", 255, 255, 255),
+            color("black", 0, 0, 0),
+            color("gray", 128, 128, 128),
+            color("dark_gray", 64, 64, 64),
+            color("light_gray", 192, 192, 192),
+        ];
+
+        assert_eq!(chain_by_similarity(&nodes, None), [1, 3, 2, 4, 0]);
+        assert_eq!(
+            chain_by_similarity(&nodes, Some(&color("", 250, 250, 250))),
+            [0, 4, 2, 3, 1]
+        );
+    }
+
+    #[test]
+    fn sampling_keeps_at_most_k_per_family() {
+        let nodes = (0..60)
+            .map(|value| color(&format!("c{}", value), value * 4, 255 - value * 2, value))
+            .chain([color("only_gray", 128, 128, 128)])
+            .collect::<Vec<_>>();
+        let families = |nodes: &[Color]| {
+            let mut counts = HashMap::new();
+
+            for color in nodes {
+                *counts.entry(color.hue_family()).or_insert(0) += 1;
+            }
+
+            counts
+        };
+        let before = families(&nodes);
+        let after = families(&sample_per_family(nodes, 2, &mut seeded_rng(Some(1))));
+
+        assert!(before.values().any(|&count| count > 2));
+        assert_eq!(
+            after.keys().collect::<HashSet<_>>(),
+            before.keys().collect()
+        );
+        assert!(after.values().all(|&count| count <= 2));
+    }
+
+    #[test]
+    fn css_themes_define_the_same_variables_twice() {
+        let nodes = fixture();
+        let css = generate_css(
+            &nodes,
+            &unique_identifiers(&nodes, "", "", &[]),
+            true,
+            CssColorSyntax::default(),
+            Rounding::Nearest,
+        )
+        .unwrap();
+        let (light, dark) = css
+            .split_once("@media (prefers-color-scheme: dark) {")
+            .unwrap();
+        let variables = |block: &str| {
+            block
+                .lines()
+                .filter_map(|line| line.trim().split_once(':'))
+                .filter(|(name, _)| name.starts_with("--"))
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(light.starts_with(":root {"));
+        assert!(dark.trim_start().starts_with(":root {"));
+        assert_eq!(variables(light).len(), nodes.len());
+        assert_eq!(variables(light), variables(dark));
+    }
+
+    #[test]
+    fn tints_head_for_white_and_shades_for_black() {
+        let base = color("teal", 0, 128, 128);
+        let colors = variants(&base, 3, Rounding::Nearest);
+        let (tints, shades) = colors.split_at(3);
+        let sum = |color: &Color| color.red as u32 + color.green as u32 + color.blue as u32;
+
+        assert_eq!(
+            names(&colors),
+            [
+                "teal_tint_1",
+                "teal_tint_2",
+                "teal_tint_3",
+                "teal_shade_1",
+                "teal_shade_2",
+                "teal_shade_3"
+            ]
+        );
+        assert!(tints.windows(2).all(|pair| sum(&pair[0]) < sum(&pair[1])));
+        assert!(sum(&tints[0]) > sum(&base) && sum(&tints[2]) < 3 * 255);
+        assert!(shades.windows(2).all(|pair| sum(&pair[0]) > sum(&pair[1])));
+        assert!(sum(&shades[0]) < sum(&base) && sum(&shades[2]) > 0);
+    }
+
+    #[test]
+    fn out_of_range_imports_clamp_unless_strict() {
+        let imported = || ImportedColor {
+            name: "red".to_string(),
+            red: 300.0,
+            green: -4.0,
+            blue: 12.6,
+        };
+
+        let color = imported().validate(false).unwrap();
+
+        assert_eq!((color.red, color.green, color.blue), (255, 0, 13));
+        assert!(imported().validate(true).is_err());
+    }
+
+    #[test]
+    fn tables_have_a_header_and_a_row_per_color() {
+        let table = generate_table(
+            &fixture(),
+            &[TableColumn::Name, TableColumn::Hex, TableColumn::Rgb],
+            None,
+            TableStyle::Ascii,
+            None,
+        )
+        .unwrap();
+        let lines = table
+            .lines()
+            .filter(|line| line.starts_with('|'))
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].contains("name") && lines[0].contains("hex") && lines[0].contains("rgb"));
+        assert!(lines[2].contains("#ff0000") && lines[2].contains("255, 0, 0"));
+        assert!(!table.contains('\x1b'));
+    }
+
+    #[test]
+    fn html_rows_have_unique_ids_the_index_links_to() {
+        let nodes = [
+            color("light_blue", 173, 216, 230),
+            color("light-blue", 172, 216, 230),
+            color("red", 255, 0, 0),
+        ];
+        let html =
+            generate_html(&nodes, true, None, &no_extras(&nodes), OutputEncoding::Utf8).unwrap();
+        let attributes = |prefix: &str| {
+            html.split(prefix)
+                .skip(1)
+                .map(|rest| rest.split('"').next().unwrap().to_string())
+                .collect::<HashSet<_>>()
+        };
+        let ids = attributes("<tr id=\"");
+
+        assert_eq!(ids.len(), nodes.len());
+        assert_eq!(attributes("href=\"#"), ids);
+    }
+
+    #[test]
+    fn grouped_html_ids_stay_clear_of_the_family_headings() {
+        let nodes = [
+            color("family_red", 255, 0, 0),
+            color("family-blue", 0, 0, 255),
+        ];
+        let html = generate_html(
+            &nodes,
+            true,
+            Some(GroupBy::Family),
+            &no_extras(&nodes),
+            OutputEncoding::Utf8,
+        )
+        .unwrap();
+        let ids = html
+            .split(" id=\"")
+            .skip(1)
+            .map(|rest| rest.split('"').next().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), ids.len());
+        assert!(ids.contains(&"family-red") && ids.contains(&"family-red-2"));
+    }
+}
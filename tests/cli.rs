@@ -0,0 +1,967 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Read, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/self-test.html");
+
+/// The binary with `args`, with the cache kept out of the user's home and
+/// local servers reached directly.
+fn command(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_colors"));
+
+    command
+        .args(args)
+        .env(
+            "COLORS_CACHE_DIR",
+            std::env::temp_dir().join(format!("colors-test-{}-cache", std::process::id())),
+        )
+        .env("RUST_BACKTRACE", "0")
+        .env("NO_PROXY", "127.0.0.1")
+        .env_remove("COLORS_FORMAT")
+        .env_remove("COLORS_OUTPUT")
+        .env_remove("COLORS_URL");
+
+    command
+}
+
+fn colors(args: &[&str]) -> Output {
+    command(args).output().expect("failed to run colors")
+}
+
+/// An empty directory of its own for each test.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("colors-test-{}-{}", std::process::id(), name));
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn is_empty(dir: &Path) -> bool {
+    fs::read_dir(dir).unwrap().next().is_none()
+}
+
+#[test]
+fn dry_run_writes_no_files() {
+    let dir = scratch("dry-run");
+    let file = dir.join("colors.csv");
+    let file = file.to_str().unwrap();
+    let out = dir.join("out");
+    let out = out.to_str().unwrap();
+
+    for args in [
+        vec!["-i", FIXTURE, "--dry-run", "-o", file],
+        vec!["-i", FIXTURE, "--dry-run", "--append", "-o", file],
+        vec!["-i", FIXTURE, "--dry-run", "-f", "all", "--output-dir", out],
+    ] {
+        let output = colors(&args);
+
+        assert!(output.status.success(), "{:?} failed", args);
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("would write"));
+    }
+
+    assert!(is_empty(&dir));
+}
+
+/// Runs `args` under --dry-run with -o pointing into a fresh directory and
+/// checks that nothing was created there.
+fn assert_dry_run_writes_nothing(name: &str, args: &[&str]) {
+    let dir = scratch(name);
+    let file = dir.join("out.csv");
+    let mut args = args.to_vec();
+
+    args.extend(["--dry-run", "-o", file.to_str().unwrap()]);
+
+    let output = colors(&args);
+
+    assert!(output.status.success(), "{:?} failed", args);
+    assert!(is_empty(&dir), "{:?} wrote {}", args, file.display());
+}
+
+#[test]
+fn dry_run_lookups_write_no_files() {
+    assert_dry_run_writes_nothing("dry-run-get", &["get", "red", "-i", FIXTURE]);
+    assert_dry_run_writes_nothing("dry-run-nearest", &["nearest", "#fe0101", "-i", FIXTURE]);
+}
+
+#[test]
+fn dry_run_near_duplicates_writes_no_files() {
+    assert_dry_run_writes_nothing(
+        "dry-run-near-duplicates",
+        &["-i", FIXTURE, "--near-duplicates", "50"],
+    );
+}
+
+#[test]
+fn dry_run_matrix_writes_no_files() {
+    assert_dry_run_writes_nothing(
+        "dry-run-matrix",
+        &["matrix", "red", "office_green", "-i", FIXTURE],
+    );
+}
+
+const DIFF_OLD: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/diff-old.json");
+const DIFF_NEW: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/diff-new.json");
+
+#[test]
+fn diff_reports_added_removed_and_changed() {
+    let output = colors(&["diff", DIFF_OLD, DIFF_NEW]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "change,name,old,new\n\
+         changed,gray,#808080,#788080\n\
+         added,teal,,#008080\n\
+         removed,blue,#0000ff,\n\n"
+    );
+}
+
+#[test]
+fn diff_writes_json_on_request() {
+    let output = colors(&["diff", DIFF_OLD, DIFF_NEW, "-f", "json"]);
+    let changes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(changes[0]["change"], "changed");
+    assert_eq!(changes[1]["name"], "teal");
+    assert_eq!(changes[2]["old"], "#0000ff");
+}
+
+#[test]
+fn dry_run_diff_writes_no_files() {
+    assert_dry_run_writes_nothing("dry-run-diff", &["diff", DIFF_OLD, DIFF_NEW]);
+}
+
+#[test]
+fn formats_lists_every_format_as_json() {
+    let output = colors(&["formats", "-f", "json"]);
+    let formats: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let image = formats
+        .iter()
+        .find(|format| format["name"] == "image")
+        .unwrap();
+
+    assert_eq!(image["binary"], true);
+    assert_eq!(image["requires_output"], "--output");
+
+    let csv = formats
+        .iter()
+        .find(|format| format["name"] == "csv")
+        .unwrap();
+    let all = formats
+        .iter()
+        .find(|format| format["name"] == "all")
+        .unwrap();
+
+    assert_eq!(csv["requires_output"], serde_json::Value::Null);
+    assert_eq!(all["requires_output"], "--output-dir");
+}
+
+#[test]
+fn dry_run_formats_writes_no_files() {
+    assert_dry_run_writes_nothing("dry-run-formats", &["formats"]);
+}
+
+const PALETTE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/fixtures/validate-palette.csv"
+);
+
+#[test]
+fn validate_reports_a_wrong_color_and_fails() {
+    let output = colors(&["validate", PALETTE, "-i", FIXTURE]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stdout.contains("mismatch,red,#ff0000,#fa0000"));
+    assert!(stdout.contains("unknown,mauve"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("2 matched, 1 mismatched, 1 unknown"));
+}
+
+#[test]
+fn validate_passes_a_matching_palette() {
+    let palette = scratch("validate-matching").join("palette.csv");
+
+    fs::write(
+        &palette,
+        "name,red,green,blue\nred,255,0,0\noffice_green,0,128,0\n",
+    )
+    .unwrap();
+
+    let output = colors(&["validate", palette.to_str().unwrap(), "-i", FIXTURE]);
+
+    assert!(output.status.success());
+}
+
+#[test]
+fn dry_run_validate_writes_no_files() {
+    let palette = scratch("dry-run-validate-palette").join("palette.csv");
+
+    fs::write(&palette, "name,red,green,blue\nred,255,0,0\n").unwrap();
+    assert_dry_run_writes_nothing(
+        "dry-run-validate",
+        &["validate", palette.to_str().unwrap(), "-i", FIXTURE],
+    );
+}
+
+const SECOND_PAGE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/second-page.html");
+
+#[test]
+fn inputs_merge_dropping_the_overlap() {
+    let output = colors(&["-i", FIXTURE, "-i", SECOND_PAGE, "-v"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let names = stdout
+        .lines()
+        .skip(1)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.split(',').next().unwrap())
+        .collect::<Vec<_>>();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("6 colors after merging"));
+    assert_eq!(
+        names,
+        [
+            "light_blue",
+            "red",
+            "dark_gray_x11",
+            "office_green",
+            "light_blue_2",
+            "teal"
+        ]
+    );
+}
+
+/// What the mock server answers a request with.
+#[derive(Clone, Default)]
+struct Reply {
+    body: String,
+    delay: Duration,
+    etag: Option<&'static str>,
+}
+
+/// Answers every request to a local port with `respond(method, path)`, and
+/// returns the base url.
+fn serve_with(respond: impl Fn(&str, &str) -> Reply + Send + Sync + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let respond = Arc::new(respond);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let respond = Arc::clone(&respond);
+
+            thread::spawn(move || {
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buf).unwrap();
+
+                    if read == 0 {
+                        return;
+                    }
+
+                    request.extend_from_slice(&buf[..read]);
+                }
+
+                let request = String::from_utf8_lossy(&request);
+                let mut words = request.split(' ');
+                let method = words.next().unwrap_or_default();
+                let reply = respond(method, words.next().unwrap_or_default());
+                let etag = reply
+                    .etag
+                    .map(|etag| format!("ETag: {}\r\n", etag))
+                    .unwrap_or_default();
+
+                thread::sleep(reply.delay);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+                    reply.body.len(),
+                    etag,
+                    if method == "HEAD" { "" } else { &reply.body }
+                );
+            });
+        }
+    });
+
+    base
+}
+
+/// Serves `pages` as `/0`, `/1` and so on, answering each page only after
+/// its delay.
+fn serve(pages: Vec<(String, Duration)>) -> String {
+    serve_with(move |_, path| {
+        path.trim_start_matches('/')
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| pages.get(index))
+            .map(|(body, delay)| Reply {
+                body: body.clone(),
+                delay: *delay,
+                etag: None,
+            })
+            .unwrap_or_default()
+    })
+}
+
+#[test]
+fn urls_merge_in_the_order_given() {
+    let base = serve(vec![
+        (
+            fs::read_to_string(FIXTURE).unwrap(),
+            Duration::from_millis(500),
+        ),
+        (fs::read_to_string(SECOND_PAGE).unwrap(), Duration::ZERO),
+    ]);
+    let (first, second) = (format!("{}/0", base), format!("{}/1", base));
+    let output = colors(&[
+        "--url",
+        &first,
+        "--url",
+        &second,
+        "--no-cache",
+        "-f",
+        "hex-list",
+    ]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "#add8e6\n#ff0000\n#a9a9a9\n#008000\n#acd8e6\n#008080\n\n"
+    );
+}
+
+#[test]
+fn utf16_stdout_ends_with_an_encoded_newline() {
+    let output = colors(&[
+        "-i",
+        FIXTURE,
+        "-f",
+        "hex-list",
+        "--output-encoding",
+        "utf16le",
+    ]);
+    let utf8 = colors(&["-i", FIXTURE, "-f", "hex-list"]);
+    let expected = [0xFF, 0xFE]
+        .into_iter()
+        .chain(
+            String::from_utf8(utf8.stdout)
+                .unwrap()
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        )
+        .collect::<Vec<u8>>();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+}
+
+#[test]
+fn an_empty_page_warns_once_unless_quiet() {
+    let page = scratch("empty-page").join("empty.html");
+
+    fs::write(&page, "<html><body></body></html>").unwrap();
+
+    let page = page.to_str().unwrap();
+    let loud = colors(&["-i", page]);
+    let quiet = colors(&["-i", page, "--quiet"]);
+
+    assert_eq!(
+        String::from_utf8(loud.stderr).unwrap(),
+        "warning: no colors were parsed; the page layout may have changed\n"
+    );
+    assert!(quiet.stderr.is_empty());
+}
+
+#[test]
+fn collapse_merges_runs_in_page_order_before_sorting() {
+    let page = scratch("collapse").join("runs.html");
+
+    fs::write(
+        &page,
+        r#"<div class="mw-content-ltr"><div>
+<p>Red</p><p title="(255 0 0)"></p>
+<p>Scarlet A</p><p title="(255 0 0)"></p>
+<p>Scarlet B</p><p title="(255 0 0)"></p>
+<p>Navy</p><p title="(0 0 128)"></p>
+<p>Red Again</p><p title="(255 0 0)"></p>
+</div></div>"#,
+    )
+    .unwrap();
+
+    let output = colors(&["-i", page.to_str().unwrap(), "--collapse", "--sort", "name"]);
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "name,red,green,blue,count\n\
+         navy,0,0,128,1\n\
+         red,255,0,0,3\n\
+         red_again,255,0,0,1\n\n"
+    );
+}
+
+/// The progress lines `--progress-format json` wrote to stderr.
+fn progress_lines(output: &Output) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn download_reports_the_body_size() {
+    let page = fs::read_to_string(FIXTURE).unwrap();
+    let url = format!("{}/0", serve(vec![(page.clone(), Duration::ZERO)]));
+    let output = colors(&["--url", &url, "--no-cache", "--progress-format", "json"]);
+    let fetched = progress_lines(&output)
+        .into_iter()
+        .rfind(|line| line["phase"] == "fetch")
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(fetched["bytes"], page.len());
+    assert_eq!(fetched["total"], page.len());
+}
+
+#[test]
+fn environment_sets_an_absent_flag() {
+    let output = command(&["-i", FIXTURE])
+        .env("COLORS_FORMAT", "hex-list")
+        .output()
+        .unwrap();
+    let flagged = command(&["-i", FIXTURE, "-f", "csv"])
+        .env("COLORS_FORMAT", "hex-list")
+        .output()
+        .unwrap();
+
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("#add8e6\n"));
+    assert!(String::from_utf8(flagged.stdout)
+        .unwrap()
+        .starts_with("name,red"));
+}
+
+#[test]
+fn manifest_hashes_every_file() {
+    use sha2::{Digest, Sha256};
+
+    let dir = scratch("manifest");
+    let output = colors(&[
+        "-i",
+        FIXTURE,
+        "-f",
+        "all",
+        "--output-dir",
+        dir.to_str().unwrap(),
+        "--manifest",
+    ]);
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&fs::read(dir.join("manifest.json")).unwrap()).unwrap();
+    let mut listed = manifest["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            let file = entry["file"].as_str().unwrap();
+            let data = fs::read(dir.join(file)).unwrap();
+            let sha256 = Sha256::digest(&data)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>();
+
+            assert_eq!(entry["sha256"], sha256, "{}", file);
+            assert_eq!(entry["size"], data.len(), "{}", file);
+            file.to_string()
+        })
+        .collect::<Vec<_>>();
+    let mut written = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|file| file != "manifest.json")
+        .collect::<Vec<_>>();
+
+    listed.sort();
+    written.sort();
+
+    assert!(output.status.success());
+    assert_eq!(listed, written);
+}
+
+#[test]
+fn an_empty_page_is_fetched_again() {
+    let page = fs::read_to_string(FIXTURE).unwrap();
+    let requests = AtomicUsize::new(0);
+    let base = serve_with(move |_, _| Reply {
+        // An error page the first time, then the real one.
+        body: if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+            "<html><body>try again later</body></html>".to_string()
+        } else {
+            page.clone()
+        },
+        ..Reply::default()
+    });
+    let output = colors(&["--url", &base, "--no-cache", "-f", "hex-list"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("retrying once"));
+    assert_eq!(String::from_utf8(output.stdout).unwrap().lines().count(), 5);
+}
+
+#[test]
+fn json_errors_are_one_json_object() {
+    let output = colors(&["--url", "not a url", "--no-cache", "--error-format", "json"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let error: serde_json::Value = serde_json::from_str(stderr.trim_end()).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(stderr.lines().count(), 1);
+    assert!(error["error"].is_string());
+    assert!(error["context"].is_array());
+}
+
+#[test]
+fn appending_twice_adds_no_duplicates() {
+    let file = scratch("append").join("colors.csv");
+    let file = file.to_str().unwrap();
+
+    for page in [FIXTURE, FIXTURE, SECOND_PAGE] {
+        assert!(colors(&["-i", page, "--append", "-o", file])
+            .status
+            .success());
+    }
+
+    let csv = fs::read_to_string(file).unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+
+    assert_eq!(
+        lines,
+        [
+            "name,red,green,blue",
+            "light_blue,173,216,230",
+            "red,255,0,0",
+            "dark_gray_x11,169,169,169",
+            "office_green,0,128,0",
+            "teal,0,128,128",
+        ]
+    );
+}
+
+#[test]
+fn a_byte_order_mark_is_skipped() {
+    let page = scratch("bom").join("bom.html");
+    let mut html = b"\xEF\xBB\xBF".to_vec();
+
+    html.extend(fs::read(FIXTURE).unwrap());
+    fs::write(&page, html).unwrap();
+
+    let output = colors(&["-i", page.to_str().unwrap(), "-v"]);
+    let plain = colors(&["-i", FIXTURE]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("skipped a UTF-8 byte order mark"));
+    assert_eq!(output.stdout, plain.stdout);
+}
+
+#[test]
+fn bench_parse_times_n_passes() {
+    let output = colors(&["-i", FIXTURE, "--bench-parse", "3"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(output.status.success());
+    assert!(
+        stderr.starts_with("parsed 4 colors 3 times: "),
+        "{}",
+        stderr
+    );
+    assert!(stderr.trim_end().ends_with(" median per pass"));
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_during_a_download_exits_cleanly() {
+    let base = serve_with(|_, _| Reply {
+        delay: Duration::from_secs(10),
+        ..Reply::default()
+    });
+    let child = command(&["--url", &base, "--no-cache", "--progress-format", "json"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    thread::sleep(Duration::from_millis(500));
+    assert!(Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap()
+        .success());
+
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(130));
+    assert!(stderr.ends_with("interrupted\n"), "{}", stderr);
+    assert!(!stderr.contains("panicked"));
+}
+
+#[test]
+fn max_colors_stops_a_larger_page() {
+    let capped = colors(&["-i", FIXTURE, "--max-colors", "3"]);
+    let exact = colors(&["-i", FIXTURE, "--max-colors", "4"]);
+
+    assert!(!capped.status.success());
+    assert!(capped.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&capped.stderr).contains("more than --max-colors 3 colors"));
+    assert!(exact.status.success());
+}
+
+#[test]
+fn json_progress_is_one_object_per_line() {
+    let url = serve(vec![(fs::read_to_string(FIXTURE).unwrap(), Duration::ZERO)]) + "/0";
+    let output = colors(&["--url", &url, "--no-cache", "--progress-format", "json"]);
+    let lines = progress_lines(&output);
+    let phases = lines
+        .iter()
+        .map(|line| line["phase"].as_str().unwrap())
+        .collect::<HashSet<_>>();
+
+    assert!(output.status.success());
+    assert!(!lines.is_empty());
+    assert_eq!(phases, HashSet::from(["fetch", "parse"]));
+}
+
+#[test]
+fn lookups_answer_from_the_snapshot() {
+    let page = fs::read_to_string(FIXTURE).unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&requests);
+    let url = serve_with(move |_, _| Reply {
+        // Only the first scrape gets the page, so a second would find nothing.
+        body: if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            page.clone()
+        } else {
+            String::new()
+        },
+        ..Reply::default()
+    });
+
+    assert!(colors(&["--url", &url]).status.success());
+
+    let output = colors(&["get", "red", "--url", &url]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "query,name,red,green,blue,error\nred,red,255,0,0,\n\n"
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn require_rgb_lists_every_name_without_one() {
+    let page = scratch("require-rgb").join("page.html");
+
+    fs::write(
+        &page,
+        r#"<div class="mw-content-ltr"><div>
+<p>Red</p><p title="(255 0 0)"></p>
+<p>Mystery</p>
+<p>Enigma</p>
+<p>Blue</p><p title="(0 0 255)"></p>
+</div></div>"#,
+    )
+    .unwrap();
+
+    let output = colors(&["-i", page.to_str().unwrap(), "--require-rgb"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("2 names have no RGB value: `Mystery`, `Enigma`"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn self_test_passes_under_either_spelling() {
+    for command in ["self-test", "selftest"] {
+        let output = colors(&[command]);
+
+        assert!(output.status.success(), "{} failed", command);
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("ok: parsed 4 colors"));
+    }
+}
+
+#[test]
+fn an_unchanged_etag_skips_the_scrape() {
+    let page = fs::read_to_string(FIXTURE).unwrap();
+    let base = serve_with(move |_, _| Reply {
+        body: page.clone(),
+        etag: Some("\"v1\""),
+        ..Reply::default()
+    });
+    let dir = scratch("skip-if-unchanged");
+    let file = dir.join("colors.csv");
+    let file = file.to_str().unwrap();
+    let args = [
+        "--url",
+        &base,
+        "--no-cache",
+        "--skip-if-unchanged",
+        "-o",
+        file,
+    ];
+
+    assert!(colors(&args).status.success());
+    fs::remove_file(file).unwrap();
+
+    let output = colors(&args);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(is_empty(&dir));
+}
+
+#[test]
+fn next_links_are_followed_once_each() {
+    let link = |html: String, href: &str| {
+        html.replace(
+            "</body>",
+            &format!("<a rel=\"next\" href=\"{}\">next</a></body>", href),
+        )
+    };
+    let base = serve(vec![
+        (
+            link(fs::read_to_string(FIXTURE).unwrap(), "/1"),
+            Duration::ZERO,
+        ),
+        // Back to the first page, which mustn't be scraped again.
+        (
+            link(fs::read_to_string(SECOND_PAGE).unwrap(), "/0"),
+            Duration::ZERO,
+        ),
+    ]);
+    let output = colors(&[
+        "--url",
+        &format!("{}/0", base),
+        "--no-cache",
+        "--next-selector",
+        "a[rel=next]",
+        "-f",
+        "hex-list",
+    ]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already scraped"));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "#add8e6\n#ff0000\n#a9a9a9\n#008000\n#acd8e6\n#008080\n\n"
+    );
+}
+
+#[test]
+fn the_output_extension_picks_the_format() {
+    let dir = scratch("infer-format");
+    let file = dir.join("out.json");
+    let output = colors(&["-i", FIXTURE, "-o", file.to_str().unwrap()]);
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(written.as_array().map(Vec::len), Some(4));
+
+    let output = colors(&["-i", FIXTURE, "-f", "csv", "-o", file.to_str().unwrap()]);
+
+    assert!(output.status.success());
+    assert!(fs::read_to_string(&file).unwrap().starts_with("name,"));
+}
+
+#[test]
+fn renamed_colors_are_escaped_in_csv() {
+    let dir = scratch("names-from-csv");
+    let names = dir.join("names.json");
+
+    fs::write(&names, r#"{"red": "Red, \"Warm\""}"#).unwrap();
+
+    let output = colors(&["-i", FIXTURE, "--names-from", names.to_str().unwrap()]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .any(|line| line == r#""Red, ""Warm""",255,0,0"#));
+
+    let out = dir.join("out");
+    let output = colors(&[
+        "-i",
+        FIXTURE,
+        "--names-from",
+        names.to_str().unwrap(),
+        "--split-channels",
+        "--output-dir",
+        out.to_str().unwrap(),
+    ]);
+
+    assert!(output.status.success());
+    assert!(fs::read_to_string(out.join("reds.csv"))
+        .unwrap()
+        .lines()
+        .any(|line| line == r#""Red, ""Warm""",255"#));
+}
+
+#[test]
+fn original_page_names_are_escaped_in_csv() {
+    let dir = scratch("name-case-csv");
+    let page = dir.join("page.html");
+
+    fs::write(
+        &page,
+        r#"<div class="mw-content-ltr"><div>
+        <p>Green "Office", Dark</p><p title="(0 100 0)"></p>
+        </div></div>"#,
+    )
+    .unwrap();
+
+    let output = colors(&["-i", page.to_str().unwrap(), "--name-case", "original"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "name,red,green,blue\n\"Green \"\"Office\"\", Dark\",0,100,0\n\n"
+    );
+}
+
+#[test]
+fn exported_csv_imports_back_unchanged() {
+    let dir = scratch("csv-round-trip");
+    let names = dir.join("names.json");
+    let file = dir.join("colors.csv");
+
+    fs::write(
+        &names,
+        r#"{"red": "Red, \"Warm\"", "office_green": " padded "}"#,
+    )
+    .unwrap();
+
+    let exported = colors(&[
+        "-i",
+        FIXTURE,
+        "--names-from",
+        names.to_str().unwrap(),
+        "-f",
+        "json",
+    ]);
+
+    assert!(colors(&[
+        "-i",
+        FIXTURE,
+        "--names-from",
+        names.to_str().unwrap(),
+        "-o",
+        file.to_str().unwrap(),
+    ])
+    .status
+    .success());
+
+    let imported = colors(&["import", "--file", file.to_str().unwrap(), "-f", "json"]);
+
+    assert!(
+        imported.status.success(),
+        "{}",
+        String::from_utf8_lossy(&imported.stderr)
+    );
+    assert_eq!(
+        String::from_utf8(imported.stdout).unwrap(),
+        String::from_utf8(exported.stdout).unwrap()
+    );
+}
+
+#[test]
+fn declared_encodings_match_the_output_encoding() {
+    let dir = scratch("declared-encoding");
+    let xml = dir.join("colors.xml");
+    let html = dir.join("colors.html");
+
+    for (file, encoding) in [(&xml, "latin1"), (&html, "utf16le")] {
+        let output = colors(&[
+            "-i",
+            FIXTURE,
+            "--output-encoding",
+            encoding,
+            "-o",
+            file.to_str().unwrap(),
+        ]);
+
+        assert!(output.status.success());
+    }
+
+    assert!(String::from_utf8(fs::read(&xml).unwrap())
+        .unwrap()
+        .starts_with(r#"<?xml version="1.0" encoding="ISO-8859-1"?>"#));
+
+    let html = fs::read(&html).unwrap();
+    let html = String::from_utf16(
+        &html[2..]
+            .chunks(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+
+    assert!(html.contains(r#"<meta charset="UTF-16">"#));
+}
+
+#[test]
+fn parse_checks_bypass_the_snapshot() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&requests);
+    let url = serve_with(move |_, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+
+        Reply {
+            body: r#"<div class="mw-content-ltr"><div>
+<p>Red</p><p title="(255 0 0)"></p>
+<p>Mystery</p>
+</div></div>"#
+                .to_string(),
+            ..Reply::default()
+        }
+    });
+
+    assert!(colors(&["--url", &url]).status.success());
+
+    let output = colors(&["get", "red", "--url", &url, "--require-rgb"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("`Mystery`"));
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn the_same_seed_shuffles_the_same_way() {
+    let shuffled =
+        |seed: &str| colors(&["-i", FIXTURE, "--shuffle", "--seed", seed, "-f", "hex-list"]);
+    let first = shuffled("7");
+
+    assert!(first.status.success());
+    assert_eq!(first.stdout, shuffled("7").stdout);
+    assert_ne!(
+        first.stdout,
+        colors(&["-i", FIXTURE, "-f", "hex-list"]).stdout
+    );
+}